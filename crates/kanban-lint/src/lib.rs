@@ -57,6 +57,7 @@ fn scan_cards(root: &Board) -> Result<Vec<(std::path::PathBuf, CardFile)>> {
     if base.exists() {
         for e in walkdir::WalkDir::new(&base)
             .into_iter()
+            .filter_entry(kanban_storage::is_card_entry)
             .filter_map(|e| e.ok())
         {
             if e.file_type().is_file() {
@@ -141,6 +142,126 @@ pub fn lint_relations(root: &Board) -> Result<Vec<String>> {
     Ok(issues)
 }
 
+/// Report drift between relations.ndjson and card front matter (fix with `kanban reindex --relations-only`).
+pub fn lint_relations_index(root: &Board) -> Result<Vec<String>> {
+    let drift = root.verify_relations_index()?;
+    let mut issues = vec![];
+    for e in &drift.missing {
+        issues.push(format!(
+            "relations index drift: missing {} {} -> {}",
+            e.kind, e.from, e.to
+        ));
+    }
+    for e in &drift.stale {
+        issues.push(format!(
+            "relations index drift: stale {} {} -> {}",
+            e.kind, e.from, e.to
+        ));
+    }
+    Ok(issues)
+}
+
+/// Flag relative links/images in card bodies whose target file does not exist.
+/// A target is resolved against the card's directory first, then the repository (board) root.
+pub fn lint_body_links(root: &Board) -> Result<Vec<String>> {
+    let cards = scan_cards(root)?;
+    let mut issues = vec![];
+    for (p, c) in &cards {
+        for link in kanban_model::extract_links(&c.body) {
+            let Some(rel) = link.relative_path() else {
+                continue;
+            };
+            let in_card_dir = p.parent().map(|d| d.join(rel).exists()).unwrap_or(false);
+            if !in_card_dir && !root.root.join(rel).exists() {
+                issues.push(format!(
+                    "broken link: {} -> {}",
+                    c.front_matter.id.to_uppercase(),
+                    link.target
+                ));
+            }
+        }
+    }
+    Ok(issues)
+}
+
+/// Flag front-matter `links` that repeat a URL within the same card (trailing `/` ignored).
+pub fn lint_duplicate_links(root: &Board) -> Result<Vec<String>> {
+    let cards = scan_cards(root)?;
+    let mut issues = vec![];
+    for (_p, c) in &cards {
+        let mut seen: HashSet<String> = HashSet::new();
+        for l in c.front_matter.links.iter().flatten() {
+            if !seen.insert(l.url.trim_end_matches('/').to_string()) {
+                issues.push(format!(
+                    "duplicate link: {} -> {}",
+                    c.front_matter.id.to_uppercase(),
+                    l.url
+                ));
+            }
+        }
+    }
+    Ok(issues)
+}
+
+/// Flag open cards past their `due_date` (date-only values end in the board timezone) and
+/// due dates that do not parse.
+pub fn lint_overdue(root: &Board, columns_toml: &kanban_model::ColumnsToml) -> Result<Vec<String>> {
+    let done_like = kanban_storage::DoneLike::new(columns_toml);
+    let now = kanban_model::now();
+    let mut issues = vec![];
+    for (p, column) in root.card_files() {
+        if done_like.contains(&column) {
+            continue;
+        }
+        let Ok(card) = fs_err::read_to_string(&p).map_err(anyhow::Error::from).and_then(|t| CardFile::from_markdown(&t)) else {
+            continue;
+        };
+        let Some(due) = card.front_matter.due_date.as_deref() else {
+            continue;
+        };
+        let id = card.front_matter.id.to_uppercase();
+        match kanban_model::parse_due_date_in(due, columns_toml.board_offset()) {
+            Some(at) if at < now => issues.push(format!("overdue: {id} due {due} ({column})")),
+            Some(_) => {}
+            None => issues.push(format!("invalid due_date: {id} -> {due}")),
+        }
+    }
+    issues.sort();
+    Ok(issues)
+}
+
+/// Open cards not written for longer than their column's `[render] stale_after` threshold, judged
+/// by `updated_at` (`created_at` for cards never rewritten). Nothing is reported without a threshold.
+pub fn lint_stale(root: &Board, columns_toml: &kanban_model::ColumnsToml) -> Result<Vec<String>> {
+    let done_like = kanban_storage::DoneLike::new(columns_toml);
+    let now = kanban_model::now();
+    let mut issues = vec![];
+    for (p, column) in root.card_files() {
+        let Some(threshold) = columns_toml.render.stale_threshold(&column) else {
+            continue;
+        };
+        if done_like.contains(&column) {
+            continue;
+        }
+        let Ok(card) = fs_err::read_to_string(&p).map_err(anyhow::Error::from).and_then(|t| CardFile::from_markdown(&t)) else {
+            continue;
+        };
+        let fm = &card.front_matter;
+        let Some(at) = fm.updated_at.as_deref().or(fm.created_at.as_deref()) else {
+            continue;
+        };
+        let Some(ts) = kanban_model::parse_since(at) else {
+            continue;
+        };
+        let age = now - ts;
+        if age >= threshold {
+            issues.push(format!("stale: {} unchanged for {}d ({column}, updated_at {at})", fm.id.to_uppercase(), age.whole_days()));
+        }
+    }
+    issues.sort();
+    Ok(issues)
+}
+
 pub fn lint_parent_done(root: &Board) -> Result<Vec<String>> {
     let cards = scan_cards(root)?;
     let mut by_parent: HashMap<String, Vec<CardFile>> = HashMap::new();
@@ -173,3 +294,38 @@ pub fn lint_parent_done(root: &Board) -> Result<Vec<String>> {
     }
     Ok(issues)
 }
+
+/// Every board check `kanban lint` runs (WIP, relations, parent/done, index drift, duplicate links,
+/// overdue and stale cards, and broken body links when `check_links`). A check that fails to run
+/// contributes nothing.
+pub fn lint_board(root: &Board, columns_toml: &kanban_model::ColumnsToml, check_links: bool) -> Vec<String> {
+    let mut issues: Vec<String> = vec![];
+    let checks: [&dyn Fn() -> Result<Vec<String>>; 7] = [
+        &|| lint_wip(root, columns_toml),
+        &|| lint_relations(root),
+        &|| lint_parent_done(root),
+        &|| lint_relations_index(root),
+        &|| lint_duplicate_links(root),
+        &|| lint_overdue(root, columns_toml),
+        &|| lint_stale(root, columns_toml),
+    ];
+    for check in checks {
+        issues.extend(check().unwrap_or_default());
+    }
+    if check_links {
+        issues.extend(lint_body_links(root).unwrap_or_default());
+    }
+    issues
+}
+
+/// `error` or `warn` for a lint message: missing fields, dangling references and cycles are errors.
+pub fn severity(msg: &str) -> &'static str {
+    let m = msg.to_ascii_lowercase();
+    if m.contains("missing id") || m.contains("missing title") {
+        return "error";
+    }
+    if m.contains("dangling ") || m.contains("cycle") {
+        return "error";
+    }
+    "warn"
+}
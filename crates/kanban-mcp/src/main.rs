@@ -23,8 +23,18 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Start MCP server over stdio
-    Mcp {},
-    /// Lint board (relations/parent_done/wip)
+    Mcp {
+        /// Access policy file (TOML: allow = ["read"], optional [groups]); all tools allowed when omitted
+        #[arg(long, value_name = "PATH")]
+        policy: Option<String>,
+        /// Cap on one tool result in JSON bytes; larger results are truncated (0 = no cap)
+        #[arg(long, value_name = "BYTES", default_value_t = kanban_mcp::MAX_RESPONSE_BYTES_DEFAULT)]
+        max_response_bytes: usize,
+        /// Put a one-line health summary of --board (counts, WIP violations, lint errors) in initialize instructions
+        #[arg(long)]
+        health_summary: bool,
+    },
+    /// Lint board (relations/parent_done/wip/relations index drift/duplicate links/body links)
     Lint {
         /// Output JSON array instead of human text
         #[arg(long)]
@@ -32,6 +42,9 @@ enum Commands {
         /// Fail on: error|warn (error by default)
         #[arg(long, default_value = "error")]
         fail_on: String,
+        /// Also flag relative links/images in card bodies that point to missing files
+        #[arg(long)]
+        check_links: bool,
     },
     /// Reindex cards/relations ndjson
     Reindex {
@@ -40,7 +53,7 @@ enum Commands {
         #[arg(long)]
         relations_only: bool,
     },
-    /// Compact done partitions / cleanup (safe subset)
+    /// Compact done partitions, relations.ndjson and empty dirs (safe subset)
     Compact {
         /// Show actions without applying
         #[arg(long)]
@@ -49,6 +62,12 @@ enum Commands {
         #[arg(long, default_value_t = true)]
         remove_empty_dirs: bool,
     },
+    /// Migrate mixed-case ULIDs to uppercase (card FM, filenames, notes files) and reindex
+    NormalizeIds {
+        /// Show what would change without applying
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Notes (journal) helpers
     NotesAppend {
         /// Card ULID
@@ -57,7 +76,7 @@ enum Commands {
         /// Note text (short paragraphs recommended)
         #[arg(long)]
         text: String,
-        /// Note type: worklog|resume|decision (default worklog)
+        /// Note type: worklog|resume|decision or a [notes.types] entry (default worklog)
         #[arg(long, default_value = "worklog")]
         r#type: String,
         /// Optional comma-separated tags
@@ -69,6 +88,9 @@ enum Commands {
         /// Read text from file (overrides --text)
         #[arg(long, value_name = "PATH")]
         from_file: Option<String>,
+        /// Dedup key: skip when a note with this key already exists for the card
+        #[arg(long)]
+        key: Option<String>,
     },
     NotesList {
         /// Card ULID
@@ -83,9 +105,12 @@ enum Commands {
         /// Output JSON
         #[arg(long, default_value_t = false)]
         json: bool,
-        /// Filter notes since RFC3339 timestamp (e.g., 2025-09-05T12:00:00Z)
-        #[arg(long, value_name = "RFC3339")]
+        /// Notes at/after: RFC3339 (e.g., 2025-09-05T12:00:00+09:00), YYYY-MM-DD, or an age (24h, 7d)
+        #[arg(long, value_name = "WHEN")]
         since: Option<String>,
+        /// Notes at/before: RFC3339, YYYY-MM-DD (inclusive day), or an age (1h)
+        #[arg(long, value_name = "WHEN")]
+        until: Option<String>,
         /// Output format: plain|markdown (default plain)
         #[arg(long, value_name = "FMT")]
         format: Option<String>,
@@ -96,6 +121,24 @@ enum Commands {
         #[arg(long, default_value_t = false)]
         link: bool,
     },
+    /// Export all notes of a card as a markdown document (oldest first, per-day headers)
+    NotesExport {
+        /// Card ULID
+        #[arg(long)]
+        card_id: String,
+        /// Output file (stdout when omitted)
+        #[arg(long, value_name = "PATH")]
+        out: Option<String>,
+    },
+    /// Export a parent's subtree as a work plan (nested markdown checklist with sizes, dependencies, blockers)
+    Plan {
+        /// Parent card ULID
+        #[arg(long)]
+        root: String,
+        /// Output file (stdout when omitted)
+        #[arg(long, value_name = "PATH")]
+        out: Option<String>,
+    },
     /// Update front-matter quick resume fields
     UpdateFm {
         /// Card ULID
@@ -111,6 +154,130 @@ enum Commands {
         #[arg(long, value_name = "BLOCKER")]
         blocker: Vec<String>,
     },
+    /// List cards as JSON (kanban_list filters)
+    List {
+        /// Fan out across every board in the registry (boards.toml) and tag items by board
+        #[arg(long)]
+        all_boards: bool,
+        /// Column filter (repeatable)
+        #[arg(long, value_name = "COLUMN")]
+        column: Vec<String>,
+        #[arg(long)]
+        lane: Option<String>,
+        #[arg(long)]
+        assignee: Option<String>,
+        #[arg(long)]
+        label: Option<String>,
+        #[arg(long)]
+        query: Option<String>,
+        #[arg(long)]
+        include_done: bool,
+    },
+    /// Start a new board from an existing board or a named template (columns.toml, templates/, selected cards)
+    BoardClone {
+        /// Source: board directory, registry alias, or template name (<registry dir>/templates/<name>/)
+        #[arg(long)]
+        from: String,
+        /// New board root; defaults to --board
+        #[arg(long)]
+        to: Option<String>,
+        /// Also copy this card (repeatable)
+        #[arg(long = "card", value_name = "ID")]
+        cards: Vec<String>,
+        /// Also copy cards carrying this label, e.g. recurring checklists (repeatable)
+        #[arg(long = "with-label", value_name = "LABEL")]
+        labels: Vec<String>,
+    },
+    /// Register a board in the user-level registry used by `list --all-boards`
+    BoardsAdd {
+        #[arg(long)]
+        alias: String,
+        /// Board root (directory containing .kanban/); defaults to --board
+        #[arg(long)]
+        path: Option<String>,
+    },
+    /// Write a read-only copy of the board in another layout (obsidian: vault with wiki links)
+    Export {
+        /// Output layout (currently: obsidian)
+        #[arg(long, default_value = "obsidian")]
+        format: String,
+        /// Output directory (created if missing); must be outside .kanban/
+        #[arg(long)]
+        out: String,
+    },
+    /// Mirror TODO(kanban:<ID>) markers in source code into each card's "Code references" section
+    ScanTodos {
+        /// Source directory to scan (recursive)
+        #[arg(long, default_value = ".")]
+        src: String,
+        /// Report what would change without writing cards
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Create the cards of due recurring tasks (.kanban/recurring.toml); safe to run from cron
+    RecurringRun {
+        /// Only this recurring task
+        #[arg(long)]
+        name: Option<String>,
+        /// Report due tasks without creating cards
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Create a card from one line: "P1 #backend @alice Fix login timeout due:2025-07-01"
+    Capture {
+        /// Title words mixed with P0-P3, #label, @assignee and due:<date> tokens
+        text: String,
+        /// Target column (default backlog)
+        #[arg(long)]
+        column: Option<String>,
+        /// Print the parsed fields without creating the card
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Create cards from existing material outside the board: `import markdown`, or another
+    /// tool's export with `--format`
+    #[command(args_conflicts_with_subcommands = true)]
+    Import {
+        #[command(subcommand)]
+        source: Option<ImportSource>,
+        /// Export format: taskwarrior | todo-txt | github-projects-json
+        #[arg(long, requires = "file")]
+        format: Option<String>,
+        /// Export file (`task export` JSON, todo.txt, `gh project item-list --format json`)
+        #[arg(long)]
+        file: Option<String>,
+        /// Column for open items; defaults to the first configured column (or backlog)
+        #[arg(long)]
+        column: Option<String>,
+        /// Report the conversion without writing cards
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ImportSource {
+    /// Loose Markdown files: one card per file, title from the first heading, content kept as the body
+    Markdown {
+        /// Directory holding the files
+        #[arg(long)]
+        dir: String,
+        /// Target column; defaults to the first configured column (or backlog)
+        #[arg(long)]
+        column: Option<String>,
+        /// Also import files in subdirectories
+        #[arg(long)]
+        recursive: bool,
+        /// Delete each original once its card is written
+        #[arg(long = "move", conflicts_with = "link")]
+        move_originals: bool,
+        /// Keep the originals and link each card back to its file (front-matter links)
+        #[arg(long)]
+        link: bool,
+        /// Show what would be imported without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 fn init_logging(level: &str) {
@@ -129,7 +296,7 @@ fn init_logging(level: &str) {
         .try_init();
 }
 
-fn run_mcp_stdio() {
+fn run_mcp_stdio(server: Server) {
     info!("kanban mcp (stdio) started");
     let stdin = io::stdin();
     let mut stdout = io::stdout();
@@ -146,7 +313,7 @@ fn run_mcp_stdio() {
                 let (maybe_id, resp_val) = match req {
                     Ok(v) => {
                         let maybe_id = v.get("id").cloned();
-                        match Server::handle_value(v) {
+                        match server.handle(v) {
                             Ok(r) => (maybe_id, r),
                             Err(e) => (
                                 maybe_id,
@@ -169,7 +336,7 @@ fn run_mcp_stdio() {
                     .unwrap()),
                 };
                 // Do not respond to notifications (no id per JSON-RPC spec)
-                let should_reply = !matches!(maybe_id, None);
+                let should_reply = maybe_id.is_some();
                 if should_reply {
                     let s = serde_json::to_string(&resp_val).unwrap();
                     writeln!(stdout, "{s}").ok();
@@ -191,55 +358,51 @@ fn main() {
     info!("logging initialized (level={})", cli.log_level);
 
     match cli.command {
-        Commands::Mcp {} => run_mcp_stdio(),
-        Commands::Lint { json, fail_on } => {
-            use kanban_lint::{lint_parent_done, lint_relations, lint_wip};
-            use kanban_model::ColumnsToml;
-            use kanban_storage::Board;
-            let board = Board::new(&cli.board);
-
-            let mut issues: Vec<String> = vec![];
-            if let Ok(toml_text) =
-                fs_err::read_to_string(board.root.join(".kanban").join("columns.toml"))
-            {
-                if let Ok(cfg) = toml::from_str::<ColumnsToml>(&toml_text) {
-                    if let Ok(mut w) = lint_wip(&board, &cfg) {
-                        issues.append(&mut w);
+        Commands::Mcp { policy, max_response_bytes, health_summary } => {
+            let mut config = kanban_mcp::ServerConfig {
+                max_response_bytes,
+                direct_notifications: true,
+                initialize_health: health_summary.then(|| cli.board.clone().into()),
+                ..Default::default()
+            };
+            if let Some(path) = policy {
+                let parsed = fs_err::read_to_string(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|t| kanban_model::PolicyToml::parse(&t));
+                match parsed {
+                    Ok(p) => {
+                        info!("access policy {} (allow: {})", path, p.allow.join(","));
+                        config.policy = Some(p);
+                    }
+                    Err(e) => {
+                        eprintln!("invalid --policy {path}: {e}");
+                        std::process::exit(1);
                     }
                 }
             }
-            if let Ok(mut r) = lint_relations(&board) {
-                issues.append(&mut r);
-            }
-            if let Ok(mut p) = lint_parent_done(&board) {
-                issues.append(&mut p);
-            }
+            run_mcp_stdio(Server::new(config))
+        }
+        Commands::Lint {
+            json,
+            fail_on,
+            check_links,
+        } => {
+            use kanban_model::ColumnsToml;
+            use kanban_storage::Board;
+            let board = Board::new(&cli.board);
 
-            fn classify(msg: &str) -> &'static str {
-                let m = msg.to_ascii_lowercase();
-                if m.contains("missing id") || m.contains("missing title") {
-                    return "error";
-                }
-                if m.contains("dangling ") || m.contains("cycle") {
-                    return "error";
-                }
-                if m.contains("self ") {
-                    return "warn";
-                }
-                if m.contains("wip exceeded") {
-                    return "warn";
-                }
-                if m.contains("parent done but child not complete") {
-                    return "warn";
-                }
-                "warn"
-            }
+            // columns.toml が無い・読めないときは WIP 上限が無いので WIP 検査は空になります
+            let cfg = fs_err::read_to_string(board.root.join(".kanban").join("columns.toml"))
+                .ok()
+                .and_then(|t| toml::from_str::<ColumnsToml>(&t).ok())
+                .unwrap_or_default();
+            let issues = kanban_lint::lint_board(&board, &cfg, check_links);
 
             let classified: Vec<serde_json::Value> = issues
                 .iter()
                 .map(|m| {
                     serde_json::json!({
-                        "severity": classify(m),
+                        "severity": kanban_lint::severity(m),
                         "message": m,
                     })
                 })
@@ -293,113 +456,53 @@ fn main() {
                 );
             std::process::exit(if errors.is_empty() { 0 } else { 1 });
         }
+        Commands::NormalizeIds { dry_run } => {
+            use kanban_storage::Board;
+            let board = Board::new(&cli.board);
+            match board.normalize_id_case(dry_run) {
+                Ok(report) => {
+                    println!(
+                        "{}",
+                        serde_json::json!({"dry_run": dry_run, "changed": !report.is_empty(), "report": report})
+                    );
+                }
+                Err(e) => {
+                    eprintln!("normalize-ids failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
         Commands::Compact {
             dry_run,
             remove_empty_dirs,
         } => {
-            use kanban_model::CardFile;
             use kanban_storage::Board;
             let board = Board::new(&cli.board);
-            let base = board.root.join(".kanban");
-            let done_dir = base.join("done");
-            let mut moves: Vec<(String, String)> = vec![];
-            if done_dir.exists() {
-                for e in walkdir::WalkDir::new(&done_dir)
-                    .into_iter()
-                    .filter_map(|e| e.ok())
-                {
-                    if e.file_type().is_file() {
-                        let p = e.path().to_path_buf();
-                        if !p
-                            .extension()
-                            .and_then(|s| s.to_str())
-                            .map(|s| s.eq_ignore_ascii_case("md"))
-                            .unwrap_or(false)
-                        {
-                            continue;
-                        }
-                        // is already under done/YYYY/MM/?
-                        let rel = p.strip_prefix(&done_dir).unwrap();
-                        let depth = rel.components().count();
-                        let needs_move = depth < 3; // not under YYYY/MM
-                        if needs_move {
-                            // determine year/month from completed_at or mtime
-                            let (year, month) = if let Ok(text) = fs_err::read_to_string(&p) {
-                                if let Ok(card) = CardFile::from_markdown(&text) {
-                                    if let Some(ca) = card.front_matter.completed_at.as_deref() {
-                                        if ca.len() >= 7 {
-                                            if let (Ok(y), Ok(m)) =
-                                                (ca[0..4].parse::<i32>(), ca[5..7].parse::<u8>())
-                                            {
-                                                (y, m)
-                                            } else {
-                                                (1970, 1)
-                                            }
-                                        } else {
-                                            (1970, 1)
-                                        }
-                                    } else {
-                                        (1970, 1)
-                                    }
-                                } else {
-                                    (1970, 1)
-                                }
-                            } else {
-                                (1970, 1)
-                            };
-                            let y = format!("{year:04}");
-                            let m = format!("{month:02}");
-                            let fname = p.file_name().unwrap().to_string_lossy().to_string();
-                            let dest = done_dir.join(&y).join(&m).join(&fname);
-                            moves.push((
-                                p.to_string_lossy().to_string(),
-                                dest.to_string_lossy().to_string(),
-                            ));
-                        }
-                    }
+            match board.compact(dry_run, remove_empty_dirs) {
+                Ok(report) if dry_run => {
+                    println!(
+                        "{}",
+                        serde_json::json!({"moves": report.moves, "skipped": report.skipped, "remove_empty_dirs": remove_empty_dirs})
+                    );
                 }
-            }
-            if dry_run {
-                println!(
-                    "{}",
-                    serde_json::json!({"moves": moves, "remove_empty_dirs": remove_empty_dirs})
-                );
-                return;
-            }
-            // apply moves
-            for (from, to) in &moves {
-                let fromp = std::path::Path::new(from);
-                let top = std::path::Path::new(to);
-                if let Some(parent) = top.parent() {
-                    let _ = fs_err::create_dir_all(parent);
-                }
-                let _ = fs_err::rename(fromp, top);
-            }
-            // remove empty dirs if requested
-            if remove_empty_dirs && base.exists() {
-                // Walk bottom-up
-                let mut dirs: Vec<std::path::PathBuf> = vec![];
-                for e in walkdir::WalkDir::new(&base)
-                    .into_iter()
-                    .filter_map(|e| e.ok())
-                {
-                    if e.file_type().is_dir() {
-                        dirs.push(e.path().to_path_buf());
-                    }
+                Ok(report) => {
+                    println!(
+                        "{}",
+                        serde_json::json!({
+                            "moved": report.moves.len(),
+                            "skipped": report.skipped,
+                            "removed_dirs": report.removed_dirs.len(),
+                            "relations": report.relations,
+                            "done": report.done,
+                            "ok": true
+                        })
+                    );
                 }
-                dirs.sort_by_key(|b| std::cmp::Reverse(b.components().count()));
-                for d in dirs {
-                    if d == base {
-                        continue;
-                    }
-                    if let Ok(mut it) = std::fs::read_dir(&d) {
-                        if it.next().is_none() {
-                            let _ = fs_err::remove_dir(&d);
-                        }
-                    }
+                Err(e) => {
+                    eprintln!("compact failed: {e}");
+                    std::process::exit(1);
                 }
             }
-            println!("{}", serde_json::json!({"moved": moves.len(), "ok": true}));
         }
         Commands::NotesAppend {
             card_id,
@@ -408,6 +511,7 @@ fn main() {
             tags,
             author,
             from_file,
+            key,
         } => {
             use kanban_model::NoteEntry;
             use kanban_storage::Board;
@@ -423,7 +527,7 @@ fn main() {
             } else {
                 text
             };
-            let ts = time::OffsetDateTime::now_utc()
+            let ts = kanban_model::now()
                 .format(&time::format_description::well_known::Rfc3339)
                 .unwrap_or_default();
             let tags_vec = tags.map(|s| {
@@ -432,12 +536,30 @@ fn main() {
                     .filter(|t| !t.is_empty())
                     .collect::<Vec<_>>()
             });
+            let r#type = r#type.trim().to_lowercase();
+            let notes_cfg = kanban_model::ColumnsToml::load(&board.root).notes;
+            if let Err(e) = notes_cfg.validate_note(&r#type, tags_vec.as_deref().unwrap_or_default()) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            if let Some(k) = key.as_deref() {
+                if let Ok(Some(prev)) = board.find_note_by_key(&card_id, k) {
+                    println!(
+                        "{}",
+                        serde_json::json!({"appended": false, "duplicate": true, "ts": prev.ts})
+                    );
+                    return;
+                }
+            }
             let entry = NoteEntry {
                 ts: ts.clone(),
                 type_: r#type,
                 text,
                 tags: tags_vec,
                 author,
+                decision: None,
+                key,
+                summary: None,
             };
             if let Err(e) = board.append_note(&card_id, &entry) {
                 eprintln!("append failed: {e}");
@@ -451,13 +573,25 @@ fn main() {
             limit,
             json,
             since,
+            until,
             format,
             with_header,
             link,
         } => {
             use kanban_storage::Board;
             let board = Board::new(&cli.board);
-            match board.list_notes_advanced(&card_id, Some(limit), all, since.as_deref()) {
+            let now = kanban_model::now().to_offset(kanban_model::ColumnsToml::load(&board.root).board_offset());
+            let bound = |flag: &str, s: &Option<String>, parse: fn(&str, time::OffsetDateTime) -> Option<time::OffsetDateTime>| {
+                s.as_deref().map(|v| {
+                    parse(v, now).unwrap_or_else(|| {
+                        eprintln!("invalid --{flag}: {v} (RFC3339, YYYY-MM-DD or an age like 24h/7d)");
+                        std::process::exit(1);
+                    })
+                })
+            };
+            let since_t = bound("since", &since, kanban_model::parse_since_at);
+            let until_t = bound("until", &until, kanban_model::parse_until_at);
+            match board.list_notes_advanced(&card_id, Some(limit), all, since_t, until_t) {
                 Ok(items) => {
                     if json {
                         println!("{}", serde_json::to_string_pretty(&items).unwrap());
@@ -468,13 +602,19 @@ fn main() {
                                 .ok()
                                 .map(|c| c.front_matter.title)
                                 .unwrap_or_default();
-                            if let Some(ref s) = since {
+                            let range = match (&since, &until) {
+                                (Some(s), Some(u)) => Some(format!("since {s}, until {u}")),
+                                (Some(s), None) => Some(format!("since {s}")),
+                                (None, Some(u)) => Some(format!("until {u}")),
+                                (None, None) => None,
+                            };
+                            if let Some(r) = range {
                         println!(
-                            "### Notes (latest {}) for [{}] {} (since {})",
+                            "### Notes (latest {}) for [{}] {} ({})",
                             items.len(),
                             card_id,
                             title,
-                            s
+                            r
                         );
                             } else {
                                 println!(
@@ -526,6 +666,49 @@ fn main() {
                 }
             }
         }
+        Commands::NotesExport { card_id, out } => {
+            use kanban_storage::Board;
+            let board = Board::new(&cli.board);
+            match Server::notes_export_markdown(&board, &card_id) {
+                Ok((md, count)) => match out {
+                    Some(path) => {
+                        if let Err(e) = fs_err::write(&path, md) {
+                            eprintln!("export failed: {e}");
+                            std::process::exit(1);
+                        }
+                        println!("{}", serde_json::json!({"exported": count, "path": path}));
+                    }
+                    None => print!("{md}"),
+                },
+                Err(e) => {
+                    eprintln!("export failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Plan { root, out } => {
+            use kanban_storage::Board;
+            let board = Board::new(&cli.board);
+            match Server::plan_markdown(&board, &root) {
+                Ok(plan) => match out {
+                    Some(path) => {
+                        if let Err(e) = fs_err::write(&path, &plan.markdown) {
+                            eprintln!("plan failed: {e}");
+                            std::process::exit(1);
+                        }
+                        println!(
+                            "{}",
+                            serde_json::json!({"cards": plan.cards, "done": plan.done, "openBlockers": plan.open_blockers, "path": path})
+                        );
+                    }
+                    None => print!("{}", plan.markdown),
+                },
+                Err(e) => {
+                    eprintln!("plan failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
         Commands::UpdateFm {
             card_id,
             resume_hint,
@@ -559,5 +742,306 @@ fn main() {
                 }
             }
         }
+        Commands::List {
+            all_boards,
+            column,
+            lane,
+            assignee,
+            label,
+            query,
+            include_done,
+        } => {
+            use serde_json::json;
+            let mut args = serde_json::Map::new();
+            if !column.is_empty() {
+                args.insert("columns".into(), json!(column));
+            }
+            for (k, v) in [("lane", lane), ("assignee", assignee), ("label", label), ("query", query)] {
+                if let Some(v) = v {
+                    args.insert(k.into(), json!(v));
+                }
+            }
+            if include_done {
+                args.insert("includeDone".into(), json!(true));
+            }
+            let name = if all_boards {
+                "kanban_multi_list"
+            } else {
+                args.insert("board".into(), json!(&cli.board));
+                "kanban_list"
+            };
+            let req = json!({
+                "jsonrpc":"2.0","id":1,"method":"tools/call",
+                "params":{"name": name, "arguments": serde_json::Value::Object(args)}
+            });
+            match kanban_mcp::Server::handle_value(req) {
+                Ok(v) if v.get("error").is_none() => {
+                    let mut res = v["result"].clone();
+                    if let Some(o) = res.as_object_mut() {
+                        o.remove("content");
+                    }
+                    println!("{}", serde_json::to_string_pretty(&res).unwrap_or_default());
+                }
+                Ok(v) => {
+                    eprintln!("list failed: {}", v["error"]["message"]);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("list failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::BoardsAdd { alias, path } => {
+            let reg_path = kanban_mcp::Server::registry_path();
+            let mut reg = match kanban_mcp::Server::load_registry() {
+                Ok(r) => r,
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            };
+            let root = path.clone().unwrap_or_else(|| cli.board.clone());
+            let root = std::fs::canonicalize(&root)
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or(root);
+            reg.boards.retain(|b| b.alias != alias);
+            reg.boards.push(kanban_model::RegisteredBoard {
+                alias: alias.clone(),
+                path: root.clone(),
+            });
+            if let Some(dir) = reg_path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            let text = toml::to_string(&reg).unwrap_or_default();
+            if let Err(e) = std::fs::write(&reg_path, text) {
+                eprintln!("failed to write {}: {e}", reg_path.display());
+                std::process::exit(1);
+            }
+            println!("registered {alias} -> {root} ({})", reg_path.display());
+        }
+        Commands::BoardClone {
+            from,
+            to,
+            cards,
+            labels,
+        } => {
+            use kanban_storage::Board;
+            let src = match kanban_mcp::Server::resolve_clone_source(&from) {
+                Ok(p) => p,
+                Err(e) => {
+                    eprintln!("board-clone failed: {e}");
+                    std::process::exit(1);
+                }
+            };
+            let to = to.unwrap_or_else(|| cli.board.clone());
+            match Board::new(&src).clone_layout_into(std::path::Path::new(&to), &cards, &labels) {
+                Ok(report) => {
+                    println!(
+                        "{}",
+                        serde_json::json!({"from": src.to_string_lossy(), "to": to, "files": report.files, "cards": report.cards})
+                    );
+                }
+                Err(e) => {
+                    eprintln!("board-clone failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Export { format, out } => {
+            use kanban_storage::Board;
+            if format != "obsidian" {
+                eprintln!("export failed: unknown format {format} (supported: obsidian)");
+                std::process::exit(1);
+            }
+            let board = Board::new(&cli.board);
+            let out_dir = std::path::PathBuf::from(&out);
+            let store = std::path::absolute(board.root.join(".kanban"));
+            let inside_store = match (store, std::path::absolute(&out_dir)) {
+                (Ok(s), Ok(o)) => o.starts_with(s),
+                _ => false,
+            };
+            if inside_store {
+                eprintln!("export failed: --out must be outside .kanban/");
+                std::process::exit(1);
+            }
+            let files = match kanban_render::obsidian_vault(&board) {
+                Ok(f) => f,
+                Err(e) => {
+                    eprintln!("export failed: {e}");
+                    std::process::exit(1);
+                }
+            };
+            // 前回のエクスポートで書いた、もう存在しないカードのノートを消します
+            let keep: std::collections::HashSet<String> = files.iter().map(|(p, _)| p.clone()).collect();
+            let mut removed = 0;
+            for dir in ["cards", "columns"] {
+                for e in std::fs::read_dir(out_dir.join(dir)).into_iter().flatten().flatten() {
+                    let rel = format!("{dir}/{}", e.file_name().to_string_lossy());
+                    if rel.ends_with(".md") && !keep.contains(&rel) && std::fs::remove_file(e.path()).is_ok() {
+                        removed += 1;
+                    }
+                }
+            }
+            for (rel, text) in &files {
+                let path = out_dir.join(rel);
+                let res = std::fs::create_dir_all(path.parent().unwrap_or(&out_dir)).and_then(|_| std::fs::write(&path, text));
+                if let Err(e) = res {
+                    eprintln!("export failed: {}: {e}", path.display());
+                    std::process::exit(1);
+                }
+            }
+            let cards = files.iter().filter(|(p, _)| p.starts_with("cards/")).count();
+            let columns = files.iter().filter(|(p, _)| p.starts_with("columns/")).count();
+            println!(
+                "{}",
+                serde_json::json!({"format": format, "out": out, "cards": cards, "columns": columns, "removed": removed})
+            );
+        }
+        Commands::ScanTodos { src, dry_run } => {
+            use kanban_storage::Board;
+            let board = Board::new(&cli.board);
+            match board.scan_code_todos(std::path::Path::new(&src), dry_run) {
+                Ok(report) => {
+                    let mut out = serde_json::to_value(&report).unwrap_or_default();
+                    out["dryRun"] = serde_json::json!(dry_run);
+                    println!("{out}");
+                    // 完了済み/存在しないカードを指すマーカーがあれば CI で気付けるよう失敗にします
+                    std::process::exit(if report.flagged.is_empty() { 0 } else { 1 });
+                }
+                Err(e) => {
+                    eprintln!("scan-todos failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::RecurringRun { name, dry_run } => {
+            use serde_json::json;
+            let mut args = json!({"board": &cli.board, "dryRun": dry_run});
+            if let Some(n) = name {
+                args["name"] = json!(n);
+            }
+            let req = json!({
+                "jsonrpc":"2.0","id":1,"method":"tools/call",
+                "params":{"name": "kanban_recurring_run", "arguments": args}
+            });
+            match kanban_mcp::Server::handle_value(req) {
+                Ok(v) if v.get("error").is_none() => {
+                    let mut res = v["result"].clone();
+                    if let Some(o) = res.as_object_mut() {
+                        o.remove("content");
+                    }
+                    println!("{res}");
+                    let failed = res["errors"].as_array().is_some_and(|e| !e.is_empty());
+                    std::process::exit(if failed { 1 } else { 0 });
+                }
+                Ok(v) => {
+                    eprintln!("recurring-run failed: {}", v["error"]["data"]["detail"].as_str().unwrap_or_default());
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("recurring-run failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Capture { text, column, dry_run } => {
+            use serde_json::json;
+            let mut args = json!({"board": &cli.board, "text": text, "dryRun": dry_run});
+            if let Some(c) = column {
+                args["column"] = json!(c);
+            }
+            let req = json!({
+                "jsonrpc":"2.0","id":1,"method":"tools/call",
+                "params":{"name": "kanban_capture", "arguments": args}
+            });
+            match kanban_mcp::Server::handle_value(req) {
+                Ok(v) if v.get("error").is_none() => {
+                    let mut res = v["result"].clone();
+                    if let Some(o) = res.as_object_mut() {
+                        o.remove("content");
+                    }
+                    println!("{res}");
+                }
+                Ok(v) => {
+                    eprintln!("capture failed: {}", v["error"]["data"]["detail"].as_str().unwrap_or_default());
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("capture failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Import { source: None, format, file, column, dry_run } => {
+            use kanban_storage::{Board, TaskFormat};
+            let Some(format) = format.as_deref().and_then(TaskFormat::parse) else {
+                eprintln!("import failed: pass a subcommand (markdown) or --format {}", TaskFormat::NAMES.join("|"));
+                std::process::exit(2);
+            };
+            let board = Board::new(&cli.board);
+            let cfg = kanban_model::ColumnsToml::load(&board.root);
+            let column = column
+                .or_else(|| cfg.columns.first().cloned())
+                .unwrap_or_else(|| "backlog".into());
+            if board.done_like().contains(&column) {
+                eprintln!("import failed: {column} is a done column; import into an open column");
+                std::process::exit(1);
+            }
+            let file = file.unwrap_or_default();
+            match board.import_tasks(std::path::Path::new(&file), format, &column, dry_run) {
+                Ok(report) => {
+                    let mut out = serde_json::to_value(&report).unwrap_or_default();
+                    out["dryRun"] = serde_json::json!(dry_run);
+                    println!("{out}");
+                }
+                Err(e) => {
+                    eprintln!("import failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Import {
+            source:
+                Some(ImportSource::Markdown {
+                    dir,
+                    column,
+                    recursive,
+                    move_originals,
+                    link,
+                    dry_run,
+                }),
+            ..
+        } => {
+            use kanban_storage::{Board, ImportOriginals};
+            let board = Board::new(&cli.board);
+            let cfg = kanban_model::ColumnsToml::load(&board.root);
+            let column = column
+                .or_else(|| cfg.columns.first().cloned())
+                .unwrap_or_else(|| "backlog".into());
+            if board.done_like().contains(&column) {
+                eprintln!("import failed: {column} is a done column; import into an open column");
+                std::process::exit(1);
+            }
+            let originals = if move_originals {
+                ImportOriginals::Move
+            } else if link {
+                ImportOriginals::Link
+            } else {
+                ImportOriginals::Keep
+            };
+            match board.import_markdown_dir(std::path::Path::new(&dir), &column, recursive, originals, dry_run) {
+                Ok(report) => {
+                    let mut out = serde_json::to_value(&report).unwrap_or_default();
+                    out["column"] = serde_json::json!(column);
+                    out["dryRun"] = serde_json::json!(dry_run);
+                    println!("{out}");
+                }
+                Err(e) => {
+                    eprintln!("import failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 }
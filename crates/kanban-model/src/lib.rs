@@ -1,14 +1,141 @@
 use anyhow::Result;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime, UtcOffset};
 use ulid::Ulid;
 
-/// ULID utilities (uppercase, 26 chars)
+/// ULID utilities (uppercase, 26 chars). Drawn from the injected [`IdGen`], if any.
 pub fn new_ulid() -> String {
-    Ulid::new().to_string().to_uppercase()
+    let injected = INJECTION.with(|i| i.borrow().ids.clone());
+    match injected {
+        Some(ids) => ids.new_id().to_uppercase(),
+        None => UlidGen.new_id(),
+    }
+}
+
+/// Current time for `created_at`/`completed_at`, note timestamps and relative-time defaults.
+/// Read from the injected [`Clock`], if any.
+pub fn now() -> OffsetDateTime {
+    let injected = INJECTION.with(|i| i.borrow().clock.clone());
+    match injected {
+        Some(clock) => clock.now(),
+        None => SystemClock.now(),
+    }
+}
+
+/// Source of "now" (see [`inject`]).
+pub trait Clock: Send + Sync {
+    fn now(&self) -> OffsetDateTime;
+}
+
+/// Source of new card ids (see [`inject`]).
+pub trait IdGen: Send + Sync {
+    fn new_id(&self) -> String;
+}
+
+/// Wall clock (UTC); the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// Random ULIDs; the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UlidGen;
+
+impl IdGen for UlidGen {
+    fn new_id(&self) -> String {
+        Ulid::new().to_string().to_uppercase()
+    }
+}
+
+/// Clock that stands still until it is `set` or `advance`d.
+#[derive(Debug)]
+pub struct FixedClock(std::sync::Mutex<OffsetDateTime>);
+
+impl FixedClock {
+    pub fn new(at: OffsetDateTime) -> Self {
+        Self(std::sync::Mutex::new(at))
+    }
+
+    pub fn set(&self, at: OffsetDateTime) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = at;
+    }
+
+    pub fn advance(&self, by: time::Duration) {
+        let mut at = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        *at += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Deterministic ULIDs: the timestamp part is fixed and the random part counts up from 1,
+/// so ids are valid, unique and sort in creation order.
+#[derive(Debug)]
+pub struct SequentialIds {
+    timestamp_ms: u64,
+    next: std::sync::atomic::AtomicU64,
+}
+
+impl SequentialIds {
+    pub fn new(at: OffsetDateTime) -> Self {
+        Self {
+            timestamp_ms: (at.unix_timestamp_nanos() / 1_000_000).max(0) as u64,
+            next: std::sync::atomic::AtomicU64::new(1),
+        }
+    }
+}
+
+impl IdGen for SequentialIds {
+    fn new_id(&self) -> String {
+        let n = self.next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Ulid::from_parts(self.timestamp_ms, n as u128).to_string()
+    }
+}
+
+/// Clock and id generator used by [`now`] and [`new_ulid`] on the current thread;
+/// `None` means the real implementation.
+#[derive(Clone, Default)]
+pub struct Injection {
+    pub clock: Option<std::sync::Arc<dyn Clock>>,
+    pub ids: Option<std::sync::Arc<dyn IdGen>>,
+}
+
+thread_local! {
+    static INJECTION: std::cell::RefCell<Injection> = std::cell::RefCell::new(Injection::default());
+}
+
+/// Use `injection` on this thread until the guard is dropped (the previous one is restored).
+/// Threads spawned on behalf of the caller (the MCP watcher) carry it over via [`current_injection`].
+#[must_use = "the injection is undone when the guard is dropped"]
+pub fn inject(injection: Injection) -> InjectionGuard {
+    InjectionGuard(Some(INJECTION.with(|i| i.replace(injection))))
+}
+
+/// The injection active on this thread, to install in a helper thread.
+pub fn current_injection() -> Injection {
+    INJECTION.with(|i| i.borrow().clone())
+}
+
+pub struct InjectionGuard(Option<Injection>);
+
+impl Drop for InjectionGuard {
+    fn drop(&mut self) {
+        if let Some(prev) = self.0.take() {
+            INJECTION.with(|i| *i.borrow_mut() = prev);
+        }
+    }
 }
 
 /// Column definitions loaded from `.kanban/columns.toml` (placeholder)
@@ -20,10 +147,26 @@ pub struct WatchToml {
     pub debounce_ms: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_batch: Option<usize>,
+    /// Max card notifications per second (sliding window); unlimited when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rate_per_sec: Option<u32>,
+    /// What to do when a flush exceeds the rate: `board-only` (default) or `truncate`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burst_policy: Option<String>,
+    /// Consecutive overflow events that count as a burst (default 3)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burst_overflows: Option<usize>,
+    /// Restarts allowed after the watcher dies before giving up (default 5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_restarts: Option<u32>,
+    /// First restart delay in ms; doubles per restart up to 60s (default 1000)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restart_backoff_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ColumnsToml {
+    #[serde(default)]
     pub columns: Vec<String>,
     #[serde(default)]
     pub wip_limits: HashMap<String, usize>,
@@ -33,6 +176,131 @@ pub struct ColumnsToml {
     pub writer: WriterToml,
     #[serde(default)]
     pub render: RenderToml,
+    #[serde(default)]
+    pub reminders: RemindersToml,
+    #[serde(default)]
+    pub limits: LimitsToml,
+    #[serde(default)]
+    pub board: BoardToml,
+    /// Named size scale (e.g. `S = 1`, `M = 3`); card files always store the numeric weight
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sizes: BTreeMap<String, u32>,
+    /// Terminal columns besides `done` (`[terminal.cancelled]`); `[terminal.done]` overrides done's defaults
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub terminal: BTreeMap<String, TerminalToml>,
+    #[serde(default)]
+    pub notes: NotesToml,
+    #[serde(default)]
+    pub list: ListToml,
+    #[serde(default)]
+    pub labels: LabelsToml,
+    #[serde(default)]
+    pub people: PeopleToml,
+    #[serde(default)]
+    pub workflow: WorkflowToml,
+}
+
+impl ColumnsToml {
+    /// `.kanban/columns.toml` under `board_root`; defaults when missing or invalid.
+    pub fn load(board_root: &std::path::Path) -> Self {
+        std::fs::read_to_string(board_root.join(".kanban").join("columns.toml"))
+            .ok()
+            .and_then(|t| toml::from_str(&t).ok())
+            .unwrap_or_default()
+    }
+
+    /// Routing for cards entering `column`, if it is terminal. `done` always is
+    /// (partitioned by year/month, completed_at stamped) unless `[terminal.done]` says otherwise.
+    pub fn terminal_policy(&self, column: &str) -> Option<TerminalPolicy> {
+        let t = self
+            .terminal
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(column))
+            .map(|(_, v)| v);
+        if t.is_none() && !column.eq_ignore_ascii_case("done") {
+            return None;
+        }
+        let t = t.cloned().unwrap_or_default();
+        Some(TerminalPolicy {
+            partition: match t.partition.as_deref().map(str::to_ascii_lowercase).as_deref() {
+                Some("none") => Partition::None,
+                Some("yyyy") => Partition::Year,
+                _ => Partition::YearMonth,
+            },
+            stamp_completed_at: t.stamp_completed_at.unwrap_or(true),
+            offset: self.board_offset(),
+        })
+    }
+
+    /// The board's `[board] timezone` offset; UTC when unset or not a fixed offset.
+    pub fn board_offset(&self) -> UtcOffset {
+        self.board
+            .timezone
+            .as_deref()
+            .and_then(parse_utc_offset)
+            .unwrap_or(UtcOffset::UTC)
+    }
+
+    /// Whether `column` is terminal (`done` or a `[terminal.*]` entry).
+    pub fn is_terminal(&self, column: &str) -> bool {
+        self.terminal_policy(column).is_some()
+    }
+
+    /// Resolve a size given as a number ("3") or a scale name ("m", case-insensitive).
+    pub fn parse_size(&self, s: &str) -> Option<u32> {
+        let s = s.trim();
+        if let Ok(n) = s.parse::<u32>() {
+            return Some(n);
+        }
+        self.sizes
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(s))
+            .map(|(_, v)| *v)
+    }
+
+    /// Host part of `kanban://` URIs for this board: `[board] alias`, or `local` when unset/invalid.
+    pub fn board_alias(&self) -> &str {
+        match self.board.alias.as_deref().map(str::trim) {
+            Some(a)
+                if !a.is_empty()
+                    && a.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) =>
+            {
+                a
+            }
+            _ => "local",
+        }
+    }
+
+    /// Symbolic name for a numeric size, if the scale defines one.
+    pub fn size_label(&self, n: u32) -> Option<String> {
+        self.sizes
+            .iter()
+            .find(|(_, v)| **v == n)
+            .map(|(k, _)| k.clone())
+    }
+
+    /// `[list] default_sort` with unknown keys dropped; `None` when unset (or nothing valid remains).
+    pub fn configured_sort(&self) -> Option<Vec<SortKey>> {
+        let keys: Vec<SortKey> = self
+            .list
+            .default_sort
+            .as_deref()?
+            .iter()
+            .filter_map(|s| SortKey::parse(s))
+            .collect();
+        (!keys.is_empty()).then_some(keys)
+    }
+
+    /// Sort applied to list views: the configured policy, else manual position
+    /// (cards without one last) then id (= creation) order.
+    pub fn default_sort(&self) -> Vec<SortKey> {
+        self.configured_sort().unwrap_or_else(|| {
+            vec![
+                SortKey { field: SortField::Position, desc: false },
+                SortKey { field: SortField::Id, desc: false },
+            ]
+        })
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -70,6 +338,34 @@ pub struct CardFrontMatter {
     pub created_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub completed_at: Option<String>,
+    /// Last write of the card file (RFC3339), stamped by every write through `write_card_atomic`
+    /// and by note appends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// How the card ended (`completed`, `wontfix`, `duplicate`, ...); set when it is done
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<String>,
+    /// Actual size once finished (same scale as `size`), for estimate-vs-actual reporting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_size: Option<u32>,
+    /// Time spent, as an age string (`90m`, `6h`, `2d`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_spent: Option<String>,
+    /// Order within the column (ascending; cards without a position sort last, then by id)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<i64>,
+    /// Due date: `YYYY-MM-DD` or full RFC3339
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<String>,
+    /// External references (issues, PRs, docs)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<Vec<CardLink>>,
+    /// Checklist items (kanban_checklist_add / kanban_checklist_toggle)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checklist: Option<Vec<ChecklistItem>>,
+    /// Files stored under `.kanban/attachments/<ID>/` (kanban_attach)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<Vec<Attachment>>,
     // Optional fields for quick resume (LLM-friendly)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resume_hint: Option<String>,
@@ -77,6 +373,68 @@ pub struct CardFrontMatter {
     pub next_steps: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub blockers: Option<Vec<String>>,
+    /// Blocked marker (set by kanban_block, cleared by kanban_unblock once no blockers remain)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked: Option<bool>,
+    /// Column the card was deleted from, while it sits in `.kanban/.trash/`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<String>,
+}
+
+impl CardFrontMatter {
+    /// Whether a `[workflow] require` field is filled in. Lists count when non-empty;
+    /// `checklist_complete` holds when no checklist item is left unchecked.
+    pub fn has_field(&self, name: &str) -> bool {
+        let text = |v: &Option<String>| v.as_deref().is_some_and(|s| !s.trim().is_empty());
+        let list = |v: &Option<Vec<String>>| v.as_deref().is_some_and(|l| !l.is_empty());
+        match name {
+            "title" => !self.title.trim().is_empty(),
+            "description" => text(&self.description),
+            "lane" => text(&self.lane),
+            "priority" => text(&self.priority),
+            "size" => self.size.is_some(),
+            "actual_size" => self.actual_size.is_some(),
+            "time_spent" => self.time_spent.is_some(),
+            "due_date" => text(&self.due_date),
+            "resolution" => text(&self.resolution),
+            "parent" => text(&self.parent),
+            "labels" => list(&self.labels),
+            "assignees" => list(&self.assignees),
+            "depends_on" => list(&self.depends_on),
+            "checklist" => self.checklist.as_deref().is_some_and(|c| !c.is_empty()),
+            "checklist_complete" => self.checklist.iter().flatten().all(|i| i.done),
+            _ => false,
+        }
+    }
+
+    /// `(checked, items)` of a non-empty checklist.
+    pub fn checklist_progress(&self) -> Option<(usize, usize)> {
+        let items = self.checklist.as_deref().filter(|c| !c.is_empty())?;
+        Some((items.iter().filter(|i| i.done).count(), items.len()))
+    }
+
+    /// Uppercase the card id and every id reference (parent/depends_on/relates).
+    /// Returns true when anything changed.
+    pub fn normalize_ids(&mut self) -> bool {
+        fn up(s: &mut String) -> bool {
+            let u = s.to_uppercase();
+            let changed = *s != u;
+            *s = u;
+            changed
+        }
+        let mut changed = up(&mut self.id);
+        if let Some(p) = self.parent.as_mut() {
+            changed |= up(p);
+        }
+        for v in [self.depends_on.as_mut(), self.relates.as_mut()].into_iter().flatten() {
+            for s in v.iter_mut() {
+                changed |= up(s);
+            }
+        }
+        changed
+    }
 }
 
 /// Card file wrapper (YAML front matter + Markdown body)
@@ -87,8 +445,13 @@ pub struct CardFile {
 }
 
 impl CardFile {
+    /// Stamp `updated_at` with the current time.
+    pub fn touch(&mut self) {
+        self.front_matter.updated_at = now().format(&Rfc3339).ok();
+    }
+
     pub fn new_with_title(title: &str) -> Self {
-        let now = OffsetDateTime::now_utc();
+        let now = now();
         let id = new_ulid();
         Self {
             front_matter: CardFrontMatter {
@@ -129,6 +492,306 @@ impl CardFile {
     }
 }
 
+/// Parse a due date (`YYYY-MM-DD` or RFC3339). Date-only values mean the end of that day (UTC).
+pub fn parse_due_date(s: &str) -> Option<OffsetDateTime> {
+    parse_due_date_in(s, UtcOffset::UTC)
+}
+
+/// Like `parse_due_date`, with date-only values ending at midnight in `offset`.
+pub fn parse_due_date_in(s: &str, offset: UtcOffset) -> Option<OffsetDateTime> {
+    let s = s.trim();
+    if let Ok(t) = OffsetDateTime::parse(s, &Rfc3339) {
+        return Some(t);
+    }
+    let d = time::Date::parse(s, time::macros::format_description!("[year]-[month]-[day]")).ok()?;
+    Some(d.with_hms(23, 59, 59).ok()?.assume_offset(offset))
+}
+
+/// Lower bound of a time range: RFC3339, or `YYYY-MM-DD` as the start of that day (UTC)
+pub fn parse_since(s: &str) -> Option<OffsetDateTime> {
+    parse_since_in(s, UtcOffset::UTC)
+}
+
+/// Like `parse_since`, with `YYYY-MM-DD` starting at midnight in `offset`.
+pub fn parse_since_in(s: &str, offset: UtcOffset) -> Option<OffsetDateTime> {
+    let s = s.trim();
+    if let Ok(t) = OffsetDateTime::parse(s, &Rfc3339) {
+        return Some(t);
+    }
+    let d = time::Date::parse(s, time::macros::format_description!("[year]-[month]-[day]")).ok()?;
+    Some(d.midnight().assume_offset(offset))
+}
+
+/// Upper bound of a time range: RFC3339, or `YYYY-MM-DD` as the end of that day (UTC)
+pub fn parse_until(s: &str) -> Option<OffsetDateTime> {
+    parse_due_date(s)
+}
+
+/// Like `parse_until`, with `YYYY-MM-DD` ending in `offset`.
+pub fn parse_until_in(s: &str, offset: UtcOffset) -> Option<OffsetDateTime> {
+    parse_due_date_in(s, offset)
+}
+
+/// Fixed UTC offset: `+09:00`, `-0530`, `+09`, or `UTC`/`Z`
+pub fn parse_utc_offset(s: &str) -> Option<UtcOffset> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("z") {
+        return Some(UtcOffset::UTC);
+    }
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1i8, &s[1..]),
+        b'-' => (-1i8, &s[1..]),
+        _ => return None,
+    };
+    let digits = rest.replacen(':', "", 1);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (h, m) = match digits.len() {
+        2 => (digits.parse::<i8>().ok()?, 0),
+        4 => (digits[..2].parse::<i8>().ok()?, digits[2..].parse::<i8>().ok()?),
+        _ => return None,
+    };
+    if h > 14 || m >= 60 {
+        return None;
+    }
+    UtcOffset::from_hms(sign * h, sign * m, 0).ok()
+}
+
+/// Age spec such as `30m`, `12h`, `7d`, `2w` (a bare number means days)
+pub fn parse_age(s: &str) -> Option<time::Duration> {
+    let s = s.trim();
+    let (num, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => (&s[..i], s[i..].trim()),
+        None => (s, "d"),
+    };
+    let n: i64 = num.parse().ok()?;
+    match unit.to_ascii_lowercase().as_str() {
+        "m" | "min" => Some(time::Duration::minutes(n)),
+        "h" => Some(time::Duration::hours(n)),
+        "d" => Some(time::Duration::days(n)),
+        "w" => Some(time::Duration::weeks(n)),
+        _ => None,
+    }
+}
+
+/// Fields of a quick-capture line (`P1 #backend @alice Fix login timeout due:2025-07-01`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureLine {
+    pub title: String,
+    pub priority: Option<String>,
+    pub labels: Vec<String>,
+    pub assignees: Vec<String>,
+    pub due_date: Option<String>,
+}
+
+/// Split a free-text line into card fields: `P0`..`P3` is the priority, `#label` a label,
+/// `@name` an assignee and `due:<date>` the due date; every other word stays in the title, in
+/// order. `#` followed only by digits (an issue reference such as `#42`) is kept in the title;
+/// a repeated priority or due date keeps the last one. The due date is not validated here.
+pub fn parse_capture(line: &str) -> CaptureLine {
+    let mut out = CaptureLine::default();
+    let mut words = vec![];
+    for w in line.split_whitespace() {
+        let upper = w.to_ascii_uppercase();
+        if matches!(upper.as_str(), "P0" | "P1" | "P2" | "P3") {
+            out.priority = Some(upper);
+        } else if let Some(l) = w.strip_prefix('#').filter(|l| !l.is_empty() && !l.chars().all(|c| c.is_ascii_digit())) {
+            if !out.labels.iter().any(|x| x == l) {
+                out.labels.push(l.to_string());
+            }
+        } else if let Some(a) = w.strip_prefix('@').filter(|a| !a.is_empty()) {
+            if !out.assignees.iter().any(|x| x == a) {
+                out.assignees.push(a.to_string());
+            }
+        } else if let Some(d) = w.get(..4).filter(|p| p.eq_ignore_ascii_case("due:")).map(|_| &w[4..]).filter(|d| !d.is_empty()) {
+            out.due_date = Some(d.to_string());
+        } else {
+            words.push(w);
+        }
+    }
+    out.title = words.join(" ");
+    out
+}
+
+/// Lower time bound: RFC3339 (any offset), `YYYY-MM-DD` (in `now`'s offset), or an age
+/// relative to `now` (`24h`, `7d`)
+pub fn parse_since_at(s: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    parse_since_in(s, now.offset()).or_else(|| parse_age(s).map(|age| now - age))
+}
+
+/// Upper time bound: RFC3339 (any offset), `YYYY-MM-DD` (end of day in `now`'s offset), or an
+/// age relative to `now`
+pub fn parse_until_at(s: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    parse_until_in(s, now.offset()).or_else(|| parse_age(s).map(|age| now - age))
+}
+
+/// Five-field cron schedule (`minute hour day-of-month month day-of-week`) with `*`, lists,
+/// ranges, `/step`, month/weekday names (`JAN`, `MON`) and the `@hourly`/`@daily`/`@weekly`/
+/// `@monthly`/`@yearly` shorthands. As in cron, when both day fields are restricted a day
+/// matching either one counts. Times are matched in the offset of the instant they are given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    days_any: bool,
+    weekdays_any: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let expr = expr.trim();
+        let expanded = match expr.to_ascii_lowercase().as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            _ => expr,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [min, hour, dom, mon, dow] = fields[..] else {
+            anyhow::bail!("cron schedule needs 5 fields (minute hour day month weekday): {expr:?}");
+        };
+        const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+        const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+        let mut weekdays = cron_field(dow, 0, 7, &WEEKDAYS, 0)?;
+        // 7 is Sunday too
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+        weekdays.truncate(7);
+        Ok(Self {
+            minutes: cron_field(min, 0, 59, &[], 0)?,
+            hours: cron_field(hour, 0, 23, &[], 0)?,
+            days: cron_field(dom, 1, 31, &[], 0)?,
+            months: cron_field(mon, 1, 12, &MONTHS, 1)?,
+            weekdays,
+            days_any: dom == "*",
+            weekdays_any: dow == "*",
+        })
+    }
+
+    fn day_matches(&self, date: time::Date) -> bool {
+        if !self.months[u8::from(date.month()) as usize] {
+            return false;
+        }
+        let dom = self.days[date.day() as usize];
+        let dow = self.weekdays[date.weekday().number_days_from_sunday() as usize];
+        match (self.days_any, self.weekdays_any) {
+            (false, false) => dom || dow,
+            _ => dom && dow,
+        }
+    }
+
+    /// Latest occurrence at or before `at` and after `after`; the search looks back at most 400 days.
+    pub fn latest_between(&self, after: Option<OffsetDateTime>, at: OffsetDateTime) -> Option<OffsetDateTime> {
+        let at = at.replace_second(0).ok()?.replace_nanosecond(0).ok()?;
+        let mut date = at.date();
+        for back in 0..=400 {
+            if self.day_matches(date) {
+                let (max_h, max_m) = if back == 0 { (at.hour(), at.minute()) } else { (23, 59) };
+                for h in (0..=max_h).rev().filter(|h| self.hours[*h as usize]) {
+                    let top = if h == max_h { max_m } else { 59 };
+                    if let Some(m) = (0..=top).rev().find(|m| self.minutes[*m as usize]) {
+                        let t = date.with_hms(h, m, 0).ok()?.assume_offset(at.offset());
+                        return Some(t).filter(|t| after.is_none_or(|a| *t > a));
+                    }
+                }
+            }
+            date = date.previous_day()?;
+            let day_end = date.with_hms(23, 59, 59).ok()?.assume_offset(at.offset());
+            if after.is_some_and(|a| day_end <= a) {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// One cron field as a lookup table indexed by value (`0..=max`).
+fn cron_field(field: &str, min: u8, max: u8, names: &[&str], name_base: u8) -> Result<Vec<bool>> {
+    let value = |s: &str| -> Result<u8> {
+        if let Some(i) = names.iter().position(|n| n.eq_ignore_ascii_case(s)) {
+            return Ok(i as u8 + name_base);
+        }
+        match s.parse::<u8>() {
+            Ok(n) if (min..=max).contains(&n) => Ok(n),
+            _ => anyhow::bail!("cron value {s:?} is outside {min}-{max}"),
+        }
+    };
+    let mut out = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, st)) => (r, st.parse::<u8>().ok().filter(|n| *n > 0).ok_or_else(|| anyhow::anyhow!("bad cron step in {part:?}"))?),
+            None => (part, 1),
+        };
+        let (lo, hi) = match range {
+            "*" => (min, max),
+            r => match r.split_once('-') {
+                Some((a, b)) => (value(a)?, value(b)?),
+                None if step > 1 => (value(r)?, max),
+                None => (value(r)?, value(r)?),
+            },
+        };
+        if lo > hi {
+            anyhow::bail!("cron range {part:?} runs backwards");
+        }
+        for v in (lo..=hi).step_by(step as usize) {
+            out[v as usize] = true;
+        }
+    }
+    Ok(out)
+}
+
+/// `.kanban/recurring.toml`: tasks that kanban_recurring_run turns into cards on schedule.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RecurringToml {
+    #[serde(default)]
+    pub recurring: Vec<RecurringTask>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RecurringTask {
+    /// Unique key; the last materialized occurrence is tracked under it
+    pub name: String,
+    /// Cron expression (see `CronSchedule`), evaluated in the board timezone
+    pub schedule: String,
+    /// Card title; `{{date}}` becomes the occurrence date (YYYY-MM-DD)
+    pub title: String,
+    /// Card template name (`.kanban/templates/cards/<name>.md`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lane: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignees: Option<Vec<String>>,
+}
+
+impl RecurringToml {
+    /// Parse and validate: names are unique and every schedule parses.
+    pub fn parse(text: &str) -> Result<Self> {
+        let cfg: Self = toml::from_str(text)?;
+        let mut seen = std::collections::HashSet::new();
+        for t in &cfg.recurring {
+            if t.name.trim().is_empty() || !seen.insert(t.name.to_lowercase()) {
+                anyhow::bail!("recurring task names must be non-empty and unique ({:?})", t.name);
+            }
+            CronSchedule::parse(&t.schedule).map_err(|e| anyhow::anyhow!("recurring {}: {e}", t.name))?;
+        }
+        Ok(cfg)
+    }
+}
+
 /// Filename helper: "<ULID>__<slug>.md"
 pub fn filename_for(id: &str, title: &str) -> String {
     let mut slug = slug::slugify(title);
@@ -138,65 +801,1540 @@ pub fn filename_for(id: &str, title: &str) -> String {
     format!("{}__{}.md", id.to_uppercase(), slug)
 }
 
-impl fmt::Display for CardFile {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.to_markdown() {
-            Ok(s) => write!(f, "{s}"),
-            Err(_) => write!(f, "<invalid card markdown>"),
+/// Minimum `fuzzy_score` for a fuzzy list match
+pub const FUZZY_MIN_SCORE: f64 = 0.6;
+
+/// Case- and diacritics-folded word tokens (`Café Über-Login` → `cafe`, `uber`, `login`).
+pub fn fuzzy_tokens(s: &str) -> Vec<String> {
+    slug::slugify(s)
+        .split('-')
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Similarity of `query` to `text` in 0..=1. Each query token takes its best match among the
+/// text tokens (equal 1.0, prefix 0.9, otherwise normalized edit distance); the mean is scaled
+/// down slightly when the text has many more tokens than the query, so closer titles rank first.
+pub fn fuzzy_score(query: &str, text: &str) -> f64 {
+    fn edit_distance(a: &[char], b: &[char]) -> usize {
+        let mut prev: Vec<usize> = (0..=b.len()).collect();
+        for (i, ca) in a.iter().enumerate() {
+            let mut cur = vec![i + 1; b.len() + 1];
+            for (j, cb) in b.iter().enumerate() {
+                cur[j + 1] = (prev[j] + usize::from(ca != cb)).min(prev[j + 1] + 1).min(cur[j] + 1);
+            }
+            prev = cur;
         }
+        prev[b.len()]
     }
+    let q = fuzzy_tokens(query);
+    let t = fuzzy_tokens(text);
+    if q.is_empty() || t.is_empty() {
+        return 0.0;
+    }
+    let best = |qt: &String| -> f64 {
+        t.iter()
+            .map(|tt| {
+                if tt == qt {
+                    1.0
+                } else if qt.len() >= 2 && tt.starts_with(qt.as_str()) {
+                    0.9
+                } else {
+                    let (a, b): (Vec<char>, Vec<char>) = (qt.chars().collect(), tt.chars().collect());
+                    1.0 - edit_distance(&a, &b) as f64 / a.len().max(b.len()) as f64
+                }
+            })
+            .fold(0.0, f64::max)
+    };
+    let mean = q.iter().map(best).sum::<f64>() / q.len() as f64;
+    let coverage = (q.len() as f64 / t.len() as f64).min(1.0);
+    mean * (0.85 + 0.15 * coverage)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Context around the first match of a search term in a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSnippet {
+    /// ~120 chars around the match, newlines flattened, matches wrapped in `**`, `…` where cut
+    pub text: String,
+    /// Character offset of the first match in the full text
+    pub offset: usize,
+}
 
-    #[test]
-    fn ulid_is_uppercase_26() {
-        let id = new_ulid();
-        assert_eq!(id.len(), 26);
-        assert!(id.chars().all(|c| !c.is_ascii_lowercase()));
+/// Case-folded copy of `text` plus, for every folded byte, the byte range of the
+/// source char it came from (Unicode lowercase; final sigma folds to `σ`).
+fn fold_case(text: &str) -> (String, Vec<(usize, usize)>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut spans = Vec::with_capacity(text.len());
+    for (i, ch) in text.char_indices() {
+        for lc in ch.to_lowercase() {
+            let lc = if lc == 'ς' { 'σ' } else { lc };
+            folded.push(lc);
+            spans.extend(std::iter::repeat_n((i, i + ch.len_utf8()), lc.len_utf8()));
+        }
     }
+    (folded, spans)
+}
 
-    #[test]
-    fn fm_roundtrip() {
-        let mut c = CardFile::new_with_title("Hello");
-        c.body = "World".into();
-        let s = c.to_markdown().unwrap();
-        let c2 = CardFile::from_markdown(&s).unwrap();
-        assert_eq!(c2.front_matter.title, "Hello");
-        assert_eq!(c2.body.trim(), "World");
+/// Snippet around the first match of any of `terms` in `text`, matched
+/// case-insensitively (Unicode). `None` when no term occurs.
+pub fn text_snippet(text: &str, terms: &[String]) -> Option<TextSnippet> {
+    const RADIUS: usize = 60;
+    // Text and terms fold with the same function; `spans` maps matches back onto `text`
+    let (lower, spans) = fold_case(text);
+    let terms: Vec<String> = terms.iter().filter(|t| !t.is_empty()).map(|t| fold_case(t).0).collect();
+    let first = terms.iter().filter_map(|t| lower.find(t.as_str()).map(|at| spans[at].0)).min()?;
+    let floor = |mut i: usize| {
+        while !text.is_char_boundary(i) {
+            i -= 1;
+        }
+        i
+    };
+    let start = floor(first.saturating_sub(RADIUS));
+    let end = floor((first + RADIUS).min(text.len()));
+    // Highlight ranges in `text` for every (longest) match inside the window
+    let mut hits: Vec<(usize, usize)> = Vec::new();
+    let mut at = 0;
+    while at < lower.len() && spans[at].0 < end {
+        let hit = terms
+            .iter()
+            .filter(|t| lower[at..].starts_with(t.as_str()))
+            .map(|t| t.len())
+            .max();
+        match hit {
+            Some(n) => {
+                let (a, b) = (spans[at].0, spans[at + n - 1].1);
+                if a >= start && b <= end {
+                    hits.push((a, b));
+                }
+                at += n;
+            }
+            None => at += lower[at..].chars().next().map_or(1, char::len_utf8),
+        }
     }
-
-    #[test]
-    fn filename_pattern() {
-        let name = filename_for("01ABCDEFGHJKLMNPQRSTVWXYZ", "Cool Title!");
-        assert!(name.starts_with("01ABCDEFGHJKLMNPQRSTVWXYZ__cool-title"));
-        assert!(name.ends_with(".md"));
+    let mut out = String::new();
+    let mut i = start;
+    for (a, b) in hits {
+        if a < i {
+            continue;
+        }
+        out.push_str(&text[i..a]);
+        out.push_str(&format!("**{}**", &text[a..b]));
+        i = b;
     }
+    out.push_str(&text[i..end]);
+    let out = out.replace('\n', " ");
+    Some(TextSnippet {
+        text: format!(
+            "{}{}{}",
+            if start > 0 { "…" } else { "" },
+            out.trim(),
+            if end < text.len() { "…" } else { "" }
+        ),
+        offset: text[..first].chars().count(),
+    })
 }
 
+/// External reference stored in card front matter (`links: [{title, url}]`).
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
-pub struct RenderToml {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub enabled: Option<bool>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub debounce_ms: Option<u64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub progress_parent: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub progress_parents: Option<Vec<String>>, // 複数親の進捗を出力
+pub struct CardLink {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub url: String,
 }
 
-/// One journal entry (NDJSON per card)
+/// Checklist item stored in card front matter (`checklist: [{text, done}]`).
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
-pub struct NoteEntry {
-    pub ts: String,
-    #[serde(rename = "type")]
-    pub type_: String,
+pub struct ChecklistItem {
     pub text: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tags: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub author: Option<String>,
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// Attachment stored in card front matter (`attachments: [{name, size, mime_type, added_at}]`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Attachment {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_at: Option<String>,
+}
+
+/// MIME type guessed from an attachment's file extension (`application/octet-stream` if unknown).
+pub fn attachment_mime(name: &str) -> &'static str {
+    let ext = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()).unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "json" => "application/json",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "txt" | "log" | "diff" | "patch" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 with padding.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode standard or URL-safe base64; whitespace is ignored and padding is optional.
+pub fn base64_decode(text: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => anyhow::bail!("invalid base64 character {:?}", c as char),
+        };
+        acc = acc << 6 | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Ok(out)
+}
+
+/// Absolute URL check: `scheme://host...` (scheme = letter then letters/digits/`+-.`), no whitespace.
+/// `mailto:` is accepted without `//`.
+pub fn is_valid_url(url: &str) -> bool {
+    static URL: once_cell::sync::Lazy<Regex> = once_cell::sync::Lazy::new(|| {
+        Regex::new(r"^(?:[A-Za-z][A-Za-z0-9+.\-]*://[^\s/?#]+[^\s]*|mailto:[^\s@]+@[^\s@]+)$").unwrap()
+    });
+    URL.is_match(url)
+}
+
+/// A Markdown link or image reference found in a card body.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BodyLink {
+    pub text: String,
+    pub target: String,
+    pub image: bool,
+}
+
+impl BodyLink {
+    /// Relative path target (no URL scheme, not an anchor, not `mailto:`); the fragment is stripped.
+    pub fn relative_path(&self) -> Option<&str> {
+        let t = self.target.split('#').next().unwrap_or("");
+        let t = t.split('?').next().unwrap_or("");
+        if t.is_empty() || t.starts_with('/') || t.contains("://") || t.contains(':') {
+            return None;
+        }
+        Some(t)
+    }
+}
+
+/// Extract inline links `[text](target)`, images `![alt](target)` and autolinks `<https://..>`
+/// from a Markdown body, in order of appearance. Fenced code blocks and inline code are skipped.
+pub fn extract_links(body: &str) -> Vec<BodyLink> {
+    static INLINE: once_cell::sync::Lazy<Regex> = once_cell::sync::Lazy::new(|| {
+        Regex::new(r#"(!?)\[([^\]]*)\]\(\s*<?([^)\s>]+)>?(?:\s+"[^"]*")?\s*\)|<(https?://[^>\s]+)>"#)
+            .unwrap()
+    });
+    static CODE_SPAN: once_cell::sync::Lazy<Regex> =
+        once_cell::sync::Lazy::new(|| Regex::new(r"`[^`]*`").unwrap());
+    let mut out = vec![];
+    let mut in_fence = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let line = CODE_SPAN.replace_all(line, "");
+        for caps in INLINE.captures_iter(&line) {
+            if let Some(auto) = caps.get(4) {
+                out.push(BodyLink {
+                    text: auto.as_str().to_string(),
+                    target: auto.as_str().to_string(),
+                    image: false,
+                });
+            } else {
+                out.push(BodyLink {
+                    text: caps[2].to_string(),
+                    target: caps[3].to_string(),
+                    image: &caps[1] == "!",
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Card template (`.kanban/templates/cards/<name>.md`): optional front matter with defaults for
+/// kanban_new and the body skeleton below it (placeholders expanded like `templates/card.md`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CardTemplate {
+    /// What the template is for (listed by kanban_templates_list; not copied to cards)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub about: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lane: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    /// Points or a `[sizes]` name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignees: Option<Vec<String>>,
+    /// Checklist item texts, unchecked on the new card
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checklist: Option<Vec<String>>,
+    #[serde(skip)]
+    pub body: String,
+}
+
+impl CardTemplate {
+    pub fn parse(text: &str) -> Result<Self> {
+        let re = Regex::new(r"(?s)^---\r?\n(.*?)\r?\n---\r?\n(.*)$").unwrap();
+        let Some(caps) = re.captures(text) else {
+            return Ok(Self { body: text.to_string(), ..Default::default() });
+        };
+        let fm = caps.get(1).map(|m| m.as_str()).unwrap_or_default();
+        let mut tpl: Self = if fm.trim().is_empty() { Self::default() } else { serde_yaml::from_str(fm)? };
+        tpl.body = caps.get(2).map(|m| m.as_str()).unwrap_or_default().trim_start_matches(['\r', '\n']).to_string();
+        Ok(tpl)
+    }
+}
+
+/// Text of the first ATX heading (`# Title` .. `###### Title`) outside fenced code and a leading
+/// YAML front matter block; trailing closing `#`s are dropped.
+pub fn first_heading(markdown: &str) -> Option<String> {
+    let mut lines = markdown.lines().peekable();
+    if lines.peek().is_some_and(|l| l.trim_end() == "---") {
+        lines.next();
+        for l in lines.by_ref() {
+            if l.trim_end() == "---" {
+                break;
+            }
+        }
+    }
+    let mut in_fence = false;
+    for line in lines {
+        let t = line.trim_start();
+        if t.starts_with("```") || t.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let hashes = t.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&hashes) && t[hashes..].starts_with([' ', '\t']) {
+            let text = t[hashes..].trim().trim_end_matches('#').trim();
+            if !text.is_empty() {
+                return Some(text.to_string());
+            }
+        }
+    }
+    None
+}
+
+impl fmt::Display for CardFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_markdown() {
+            Ok(s) => write!(f, "{s}"),
+            Err(_) => write!(f, "<invalid card markdown>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ulid_is_uppercase_26() {
+        let id = new_ulid();
+        assert_eq!(id.len(), 26);
+        assert!(id.chars().all(|c| !c.is_ascii_lowercase()));
+    }
+
+    #[test]
+    fn injected_clock_and_ids_are_scoped_to_the_guard() {
+        let at = OffsetDateTime::parse("2025-01-02T03:04:05Z", &Rfc3339).unwrap();
+        let clock = std::sync::Arc::new(FixedClock::new(at));
+        let ids = std::sync::Arc::new(SequentialIds::new(at));
+        {
+            let _guard = inject(Injection { clock: Some(clock.clone()), ids: Some(ids) });
+            let a = CardFile::new_with_title("A");
+            let b = CardFile::new_with_title("B");
+            assert_eq!(a.front_matter.created_at.as_deref(), Some("2025-01-02T03:04:05Z"));
+            assert_eq!(a.front_matter.id, "01JGJFGR480000000000000001");
+            assert!(a.front_matter.id < b.front_matter.id);
+            clock.advance(time::Duration::hours(1));
+            assert_eq!(now(), at + time::Duration::hours(1));
+            // 別スレッドには引き継いだときだけ効きます
+            let carried = current_injection();
+            let seen = std::thread::spawn(move || {
+                let plain = now();
+                let _g = inject(carried);
+                (plain, now())
+            })
+            .join()
+            .unwrap();
+            assert_ne!(seen.0, at + time::Duration::hours(1));
+            assert_eq!(seen.1, at + time::Duration::hours(1));
+        }
+        assert_ne!(now(), at + time::Duration::hours(1));
+        assert_eq!(new_ulid().len(), 26);
+    }
+
+    #[test]
+    fn fm_roundtrip() {
+        let mut c = CardFile::new_with_title("Hello");
+        c.body = "World".into();
+        let s = c.to_markdown().unwrap();
+        let c2 = CardFile::from_markdown(&s).unwrap();
+        assert_eq!(c2.front_matter.title, "Hello");
+        assert_eq!(c2.body.trim(), "World");
+    }
+
+    #[test]
+    fn parse_age_units() {
+        assert_eq!(parse_age("7d"), Some(time::Duration::days(7)));
+        assert_eq!(parse_age("12h"), Some(time::Duration::hours(12)));
+        assert_eq!(parse_age("2w"), Some(time::Duration::weeks(2)));
+        assert_eq!(parse_age("3"), Some(time::Duration::days(3)));
+        assert_eq!(parse_age("7x"), None);
+        assert_eq!(parse_age("d"), None);
+    }
+
+    #[test]
+    fn field_change_diff_lists_changed_front_matter() {
+        let before = CardFrontMatter { id: "A".into(), title: "Old".into(), size: Some(3), ..Default::default() };
+        let after = CardFrontMatter {
+            id: "A".into(),
+            title: "New".into(),
+            labels: Some(vec!["bug".into()]),
+            description: Some("x".repeat(200)),
+            ..Default::default()
+        };
+        let changes = FieldChange::diff(&before, &after);
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["description", "labels", "size", "title"]);
+        assert_eq!(changes[0].to.as_ref().unwrap().as_str().unwrap().chars().count(), FieldChange::VALUE_MAX_CHARS);
+        assert_eq!(changes[2].from, Some(serde_json::json!(3)));
+        assert_eq!(changes[2].to, None);
+        assert!(FieldChange::diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn base64_round_trips_and_accepts_url_safe_input() {
+        for bytes in [&b""[..], b"f", b"fo", b"foo", b"foob", &[0xfb, 0xff, 0x00]] {
+            assert_eq!(base64_decode(&base64_encode(bytes)).unwrap(), bytes);
+        }
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+        assert_eq!(base64_decode("Zm9v\nYg").unwrap(), b"foob");
+        assert_eq!(base64_decode("-_8").unwrap(), [0xfb, 0xff]);
+        assert!(base64_decode("Zm9v!").is_err());
+    }
+
+    #[test]
+    fn capture_line_splits_tokens_from_title() {
+        let c = parse_capture("P1 #backend @alice Fix login timeout due:2025-07-01");
+        assert_eq!(c.title, "Fix login timeout");
+        assert_eq!(c.priority.as_deref(), Some("P1"));
+        assert_eq!(c.labels, ["backend"]);
+        assert_eq!(c.assignees, ["alice"]);
+        assert_eq!(c.due_date.as_deref(), Some("2025-07-01"));
+        let c = parse_capture("  Revert #42 fix   #ui #ui @bob p0 DUE: ");
+        assert_eq!(c.title, "Revert #42 fix DUE:");
+        assert_eq!(c.labels, ["ui"]);
+        assert_eq!(c.priority.as_deref(), Some("P0"));
+        assert!(c.due_date.is_none());
+    }
+
+    #[test]
+    fn cron_schedule_finds_latest_occurrence_in_window() {
+        let at = |s: &str| OffsetDateTime::parse(s, &Rfc3339).unwrap();
+        // 2025-03-12 is a Wednesday
+        let weekly = CronSchedule::parse("30 9 * * MON").unwrap();
+        assert_eq!(weekly.latest_between(None, at("2025-03-12T12:00:00Z")), Some(at("2025-03-10T09:30:00Z")));
+        assert_eq!(weekly.latest_between(None, at("2025-03-10T09:29:00Z")), Some(at("2025-03-03T09:30:00Z")));
+        assert_eq!(weekly.latest_between(Some(at("2025-03-10T09:30:00Z")), at("2025-03-16T23:00:00Z")), None);
+        // matched in the offset of `at`
+        let tokyo = at("2025-03-10T09:45:00+09:00");
+        assert_eq!(weekly.latest_between(None, tokyo), Some(at("2025-03-10T09:30:00+09:00")));
+
+        let steps = CronSchedule::parse("*/15 8-17 * * mon-fri").unwrap();
+        assert_eq!(steps.latest_between(None, at("2025-03-15T10:00:00Z")), Some(at("2025-03-14T17:45:00Z")));
+        // both day fields restricted: the 1st or any Friday
+        let either = CronSchedule::parse("0 0 1 * 5").unwrap();
+        assert_eq!(either.latest_between(None, at("2025-03-03T00:00:00Z")), Some(at("2025-03-01T00:00:00Z")));
+        assert_eq!(either.latest_between(None, at("2025-03-08T00:00:00Z")), Some(at("2025-03-07T00:00:00Z")));
+        assert_eq!(CronSchedule::parse("@monthly").unwrap(), CronSchedule::parse("0 0 1 * *").unwrap());
+        for bad in ["* * * *", "60 * * * *", "0 0 * * 8", "5-1 * * * *", "*/0 * * * *", "0 0 * FOO *"] {
+            assert!(CronSchedule::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn workflow_checks_transitions_and_required_fields() {
+        let cfg: ColumnsToml = toml::from_str(
+            "[workflow.transitions]\nbacklog = [\"doing\"]\nreview = []\n[workflow.require]\ndoing = [\"size\", \"assignees\"]\ndone = [\"checklist_complete\"]\n",
+        )
+        .unwrap();
+        let wf = &cfg.workflow;
+        let mut fm = CardFrontMatter { size: Some(3), ..Default::default() };
+        assert_eq!(
+            wf.violation("backlog", "done", &fm).as_deref(),
+            Some("backlog -> done is not an allowed transition (from backlog: doing)")
+        );
+        assert_eq!(wf.violation("Backlog", "doing", &fm).as_deref(), Some("entering doing requires assignees"));
+        fm.assignees = Some(vec!["alice".into()]);
+        assert_eq!(wf.violation("backlog", "doing", &fm), None);
+        assert!(wf.violation("review", "doing", &fm).unwrap().contains("(from review: none)"));
+        assert_eq!(wf.violation("review", "review", &fm), None);
+        fm.checklist = Some(vec![ChecklistItem { text: "x".into(), done: false }]);
+        assert!(wf.violation("doing", "done", &fm).is_some());
+        fm.checklist.as_mut().unwrap()[0].done = true;
+        assert_eq!(wf.violation("doing", "done", &fm), None);
+    }
+
+    #[test]
+    fn card_template_splits_defaults_from_body() {
+        let tpl = CardTemplate::parse("---\nabout: Bug report\nlabels: [bug]\nsize: M\nchecklist:\n  - reproduce\n---\n\n## Steps\n{{title}}\n").unwrap();
+        assert_eq!(tpl.about.as_deref(), Some("Bug report"));
+        assert_eq!(tpl.labels, Some(vec!["bug".to_string()]));
+        assert_eq!(tpl.size, Some(serde_json::json!("M")));
+        assert_eq!(tpl.checklist, Some(vec!["reproduce".to_string()]));
+        assert_eq!(tpl.body, "## Steps\n{{title}}\n");
+        assert_eq!(CardTemplate::parse("just a body\n").unwrap().body, "just a body\n");
+        assert!(CardTemplate::parse("---\nlabel: [typo]\n---\nx\n").is_err());
+    }
+
+    #[test]
+    fn first_heading_skips_front_matter_and_code() {
+        let md = "---\ntitle: x\n---\n```\n# not this\n```\nintro\n## Fix login ##\n# Later\n";
+        assert_eq!(first_heading(md).as_deref(), Some("Fix login"));
+        assert_eq!(first_heading("#hashtag\nplain"), None);
+    }
+
+    #[test]
+    fn time_bounds_accept_absolute_and_relative() {
+        let now = parse_since("2025-07-10T00:00:00Z").unwrap();
+        assert_eq!(parse_since_at("24h", now), Some(now - time::Duration::hours(24)));
+        assert_eq!(parse_until_at("7d", now), Some(now - time::Duration::days(7)));
+        let t = parse_since_at("2025-07-01T09:00:00+09:00", now).unwrap();
+        assert_eq!(t.unix_timestamp(), parse_since("2025-07-01T00:00:00Z").unwrap().unix_timestamp());
+        assert_eq!(parse_until_at("2025-07-01", now).unwrap().hour(), 23);
+        assert!(parse_since_at("soon", now).is_none());
+    }
+
+    #[test]
+    fn due_date_accepts_date_and_rfc3339() {
+        let d = parse_due_date("2025-07-01").unwrap();
+        assert_eq!((d.year(), u8::from(d.month()), d.day(), d.hour()), (2025, 7, 1, 23));
+        let t = parse_due_date("2025-07-01T09:00:00+09:00").unwrap();
+        assert_eq!(t.unix_timestamp(), parse_due_date("2025-07-01T00:00:00Z").unwrap().unix_timestamp());
+        assert!(parse_due_date("tomorrow").is_none());
+    }
+
+    #[test]
+    fn board_timezone_shifts_partitions_and_date_only_bounds() {
+        let cfg: ColumnsToml = toml::from_str("[board]\ntimezone = \"+09:00\"\n").unwrap();
+        let jst = cfg.board_offset();
+        assert_eq!(jst, UtcOffset::from_hms(9, 0, 0).unwrap());
+        // 2025-09-30T20:00Z is already October in +09:00
+        let at = parse_since("2025-09-30T20:00:00Z").unwrap();
+        let policy = cfg.terminal_policy("done").unwrap();
+        assert_eq!(policy.subdir(at), std::path::Path::new("2025").join("10"));
+        assert_eq!(policy.stamp(at), "2025-10-01T05:00:00+09:00");
+        assert_eq!(ColumnsToml::default().terminal_policy("done").unwrap().subdir(at), std::path::Path::new("2025").join("09"));
+        let due = parse_due_date_in("2025-07-01", jst).unwrap();
+        assert_eq!(due.unix_timestamp(), parse_since("2025-07-01T14:59:59Z").unwrap().unix_timestamp());
+        let since = parse_since_at("2025-07-01", at.to_offset(jst)).unwrap();
+        assert_eq!(since.unix_timestamp(), parse_since("2025-06-30T15:00:00Z").unwrap().unix_timestamp());
+        assert_eq!(parse_utc_offset("-0530"), UtcOffset::from_hms(-5, -30, 0).ok());
+        assert_eq!(parse_utc_offset("UTC"), Some(UtcOffset::UTC));
+        for bad in ["Asia/Tokyo", "+9", "+25:00", "09:00"] {
+            assert_eq!(parse_utc_offset(bad), None, "{bad}");
+        }
+        let bogus: ColumnsToml = toml::from_str("[board]\ntimezone = \"Asia/Tokyo\"\n").unwrap();
+        assert_eq!(bogus.board_offset(), UtcOffset::UTC);
+    }
+
+    #[test]
+    fn size_scale_parse_and_label() {
+        let cfg: ColumnsToml = toml::from_str("[sizes]\nS = 1\nM = 3\nXL = 8\n").unwrap();
+        assert_eq!(cfg.parse_size("m"), Some(3));
+        assert_eq!(cfg.parse_size("5"), Some(5));
+        assert_eq!(cfg.parse_size("XXL"), None);
+        assert_eq!(cfg.size_label(8).as_deref(), Some("XL"));
+        assert_eq!(cfg.size_label(2), None);
+    }
+
+    #[test]
+    fn extract_links_finds_links_and_images() {
+        let body = "See [spec](docs/spec.md#api) and ![shot](img/a.png \"Screen\").\n\
+                    Ref <https://example.com/x>, `[not](code.md)`\n\
+                    ```\n[fenced](x.md)\n```\n[site](https://example.com)";
+        let links = extract_links(body);
+        let targets: Vec<&str> = links.iter().map(|l| l.target.as_str()).collect();
+        assert_eq!(
+            targets,
+            vec!["docs/spec.md#api", "img/a.png", "https://example.com/x", "https://example.com"]
+        );
+        assert!(links[1].image && !links[0].image);
+        assert_eq!(links[0].relative_path(), Some("docs/spec.md"));
+        assert_eq!(links[3].relative_path(), None);
+    }
+
+    #[test]
+    fn board_alias_defaults_to_local() {
+        let cfg: ColumnsToml = toml::from_str("[board]\nalias = \"team-web\"\n").unwrap();
+        assert_eq!(cfg.board_alias(), "team-web");
+        assert_eq!(ColumnsToml::default().board_alias(), "local");
+        let bad: ColumnsToml = toml::from_str("[board]\nalias = \"/home/me\"\n").unwrap();
+        assert_eq!(bad.board_alias(), "local");
+    }
+
+    #[test]
+    fn aging_bounds_sort_and_fall_back() {
+        let cfg: ColumnsToml = toml::from_str("[render]\naging_buckets = [\"2w\", \"12h\", \"bogus\"]\n").unwrap();
+        let labels: Vec<String> = cfg.render.aging_bounds().into_iter().map(|(l, _)| l).collect();
+        assert_eq!(labels, vec!["12h", "2w"]);
+        assert_eq!(ColumnsToml::default().render.aging_bounds().len(), 3);
+    }
+
+    #[test]
+    fn fuzzy_score_folds_case_and_diacritics() {
+        assert_eq!(fuzzy_tokens("Café Über-Login"), vec!["cafe", "uber", "login"]);
+        assert_eq!(fuzzy_score("cafe", "CAFÉ"), 1.0);
+        assert!(fuzzy_score("logn fix", "Fix login page") >= FUZZY_MIN_SCORE);
+        assert!(fuzzy_score("fix login", "Fix login") > fuzzy_score("fix login", "Fix login page and tests"));
+        assert!(fuzzy_score("payment", "Write docs") < FUZZY_MIN_SCORE);
+        assert_eq!(fuzzy_score("", "anything"), 0.0);
+    }
+
+    #[test]
+    fn people_resolve_ids_and_aliases() {
+        let cfg: ColumnsToml = toml::from_str(
+            "[people]\non_unknown = \"reject\"\n[people.members.alice]\nname = \"Alice L.\"\naliases = [\"al\", \"alice@example.com\"]\n",
+        )
+        .unwrap();
+        assert!(cfg.people.is_configured() && cfg.people.rejects_unknown());
+        assert_eq!(cfg.people.resolve("Alice"), Some("alice"));
+        assert_eq!(cfg.people.resolve("ALICE@example.com"), Some("alice"));
+        assert_eq!(cfg.people.resolve("bob"), None);
+        assert!(!ColumnsToml::default().people.is_configured());
+    }
+
+    #[test]
+    fn terminal_policy_defaults_and_overrides() {
+        let cfg: ColumnsToml = toml::from_str(
+            "[terminal.cancelled]\npartition = \"yyyy\"\nstamp_completed_at = false\n",
+        )
+        .unwrap();
+        let done = cfg.terminal_policy("done").unwrap();
+        assert_eq!(done.partition, Partition::YearMonth);
+        assert!(done.stamp_completed_at);
+        let c = cfg.terminal_policy("Cancelled").unwrap();
+        assert_eq!(c.partition, Partition::Year);
+        assert!(!c.stamp_completed_at);
+        assert!(!cfg.is_terminal("doing"));
+        let at = OffsetDateTime::from_unix_timestamp(1_757_000_000).unwrap();
+        assert_eq!(Partition::YearMonth.subdir(at), std::path::Path::new("2025").join("09"));
+        assert_eq!(Partition::None.subdir(at), std::path::PathBuf::new());
+    }
+
+    #[test]
+    fn default_sort_orders_by_priority_then_creation() {
+        let cfg: ColumnsToml =
+            toml::from_str("[list]\ndefault_sort = [\"priority\", \"bogus\", \"-created_at\"]\n").unwrap();
+        let keys = cfg.default_sort();
+        assert_eq!(keys.iter().map(|k| k.name()).collect::<Vec<_>>(), vec!["priority", "-created_at"]);
+        assert_eq!(
+            ColumnsToml::default().default_sort().iter().map(|k| k.name()).collect::<Vec<_>>(),
+            vec!["position", "id"]
+        );
+        let card = |id: &str, p: Option<&str>| SortFields {
+            id: id.into(),
+            priority: p.map(str::to_string),
+            ..Default::default()
+        };
+        let mut cards = [card("01A", None), card("01B", Some("P2")), card("01C", Some("P0")), card("01D", Some("P2"))];
+        cards.sort_by(|a, b| compare_by_sort(&keys, a, b));
+        let ids: Vec<&str> = cards.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["01C", "01D", "01B", "01A"]);
+    }
+
+    #[test]
+    fn note_summary_and_split() {
+        assert_eq!(NoteEntry::summary_of("\n### Decided: ndjson\n\nbody"), "Decided: ndjson");
+        let long = "x".repeat(150);
+        assert_eq!(NoteEntry::summary_of(&long).chars().count(), 100);
+        let parts = split_note_text("aaaa\nbbbb\ncccc\n", 10);
+        assert_eq!(parts, vec!["aaaa\nbbbb\n", "cccc\n"]);
+        let parts = split_note_text(&"é".repeat(10), 7);
+        assert!(parts.iter().all(|p| p.len() <= 7));
+        assert_eq!(parts.concat(), "é".repeat(10));
+    }
+
+    #[test]
+    fn url_validation() {
+        assert!(is_valid_url("https://github.com/o/r/issues/12"));
+        assert!(is_valid_url("jira+sso://tracker.example/PROJ-1"));
+        assert!(is_valid_url("mailto:ops@example.com"));
+        assert!(!is_valid_url("github.com/o/r"));
+        assert!(!is_valid_url("https://"));
+        assert!(!is_valid_url("https://exa mple.com"));
+    }
+
+    #[test]
+    fn normalize_ids_uppercases_references() {
+        let mut fm = CardFrontMatter {
+            id: "01abc".into(),
+            parent: Some("01par".into()),
+            depends_on: Some(vec!["01DEP".into(), "01dep2".into()]),
+            ..Default::default()
+        };
+        assert!(fm.normalize_ids());
+        assert_eq!(fm.id, "01ABC");
+        assert_eq!(fm.parent.as_deref(), Some("01PAR"));
+        assert_eq!(fm.depends_on, Some(vec!["01DEP".into(), "01DEP2".into()]));
+        assert!(!fm.normalize_ids());
+    }
+
+    #[test]
+    fn text_snippet_marks_first_match_with_char_offset() {
+        let sn = text_snippet("日本語 then the Parser\ncrashed", &["parser".into()]).unwrap();
+        assert_eq!(sn.offset, 13);
+        assert_eq!(sn.text, "日本語 then the **Parser** crashed");
+        let long = format!("{}needle{}", "a".repeat(100), "b".repeat(100));
+        let sn = text_snippet(&long, &["needle".into()]).unwrap();
+        assert!(sn.text.starts_with('…') && sn.text.ends_with('…'));
+        assert_eq!(sn.offset, 100);
+        assert!(text_snippet("nothing here", &["zzz".into()]).is_none());
+    }
+
+    #[test]
+    fn text_snippet_folds_unicode_case_in_text_and_terms() {
+        // Non-ASCII uppercase in the body matches a lowercased term
+        let sn = text_snippet("Das ÜBER-Ticket", &["über".into()]).unwrap();
+        assert_eq!(sn.offset, 4);
+        assert_eq!(sn.text, "Das **ÜBER**-Ticket");
+        // Lowercasing that changes byte length still highlights the source text
+        let sn = text_snippet("x İstanbul y", &["i̇stanbul".into()]).unwrap();
+        assert_eq!(sn.text, "x **İstanbul** y");
+        assert_eq!(sn.offset, 2);
+        // Final sigma from str::to_lowercase on the query matches the body's Σ
+        let sn = text_snippet("ΟΔΟΣ 1", &["ΟΔΟΣ".to_lowercase()]).unwrap();
+        assert_eq!(sn.text, "**ΟΔΟΣ** 1");
+    }
+
+    #[test]
+    fn filename_pattern() {
+        let name = filename_for("01ABCDEFGHJKLMNPQRSTVWXYZ", "Cool Title!");
+        assert!(name.starts_with("01ABCDEFGHJKLMNPQRSTVWXYZ__cool-title"));
+        assert!(name.ends_with(".md"));
+    }
+}
+
+/// User-level board registry (`boards.toml`): `[[boards]] alias = "web", path = "/src/web"`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BoardsToml {
+    #[serde(default)]
+    pub boards: Vec<RegisteredBoard>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RegisteredBoard {
+    pub alias: String,
+    pub path: String,
+}
+
+/// Access policy for one server process (`kanban mcp --policy <file>`):
+/// `allow = ["read"]` plus optional `[groups]` overrides (`write = ["kanban_notes_append"]`).
+/// Groups are `read`, `write` and `admin`; tools not listed keep their built-in group.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PolicyToml {
+    /// Groups this server may call (default: read only)
+    #[serde(default = "PolicyToml::default_allow")]
+    pub allow: Vec<String>,
+    /// group -> tool names, overriding the built-in grouping
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for PolicyToml {
+    fn default() -> Self {
+        Self {
+            allow: Self::default_allow(),
+            groups: BTreeMap::new(),
+        }
+    }
+}
+
+impl PolicyToml {
+    pub const GROUPS: [&'static str; 3] = ["read", "write", "admin"];
+
+    fn default_allow() -> Vec<String> {
+        vec!["read".into()]
+    }
+
+    /// Parse and validate (only `read`/`write`/`admin` are accepted as group names).
+    pub fn parse(text: &str) -> Result<Self> {
+        let p: PolicyToml = toml::from_str(text)?;
+        for g in p.allow.iter().chain(p.groups.keys()) {
+            if !Self::GROUPS.contains(&g.as_str()) {
+                anyhow::bail!("unknown policy group '{g}' (read|write|admin)");
+            }
+        }
+        Ok(p)
+    }
+
+    /// Group a tool was moved to by `[groups]`, if any.
+    pub fn group_override(&self, tool: &str) -> Option<&str> {
+        self.groups
+            .iter()
+            .find(|(_, tools)| tools.iter().any(|t| t == tool))
+            .map(|(g, _)| g.as_str())
+    }
+
+    pub fn allows(&self, group: &str) -> bool {
+        self.allow.iter().any(|g| g == group)
+    }
+}
+
+/// `[notes]` section: journal entry size policy
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NotesToml {
+    /// Max bytes of one note's text (default 16384)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_entry_bytes: Option<usize>,
+    /// `reject` (default) | `split` (store as consecutive parts on line boundaries)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oversize: Option<String>,
+    /// `[notes.types.<name>]`: note types beyond the built-ins, or extra rules for a built-in
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub types: BTreeMap<String, NoteTypeToml>,
+}
+
+/// One `[notes.types.<name>]` entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NoteTypeToml {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Tags every note of this type must carry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_tags: Vec<String>,
+}
+
+impl NotesToml {
+    pub const DEFAULT_MAX_ENTRY_BYTES: usize = 16 * 1024;
+    pub const BUILTIN_TYPES: [&'static str; 3] = ["worklog", "resume", "decision"];
+
+    /// Note types in registry order: built-ins, then `[notes.types]` by name, each with its
+    /// configuration (default for an unconfigured built-in).
+    pub fn note_types(&self) -> Vec<(String, bool, NoteTypeToml)> {
+        let mut out: Vec<(String, bool, NoteTypeToml)> = Self::BUILTIN_TYPES
+            .iter()
+            .map(|t| (t.to_string(), true, self.note_type(t).cloned().unwrap_or_default()))
+            .collect();
+        for (name, cfg) in &self.types {
+            if !Self::BUILTIN_TYPES.iter().any(|b| b.eq_ignore_ascii_case(name)) {
+                out.push((name.to_ascii_lowercase(), false, cfg.clone()));
+            }
+        }
+        out
+    }
+
+    fn note_type(&self, name: &str) -> Option<&NoteTypeToml> {
+        self.types
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Check a note's type against the registry and its required tags (case-insensitive).
+    pub fn validate_note(&self, note_type: &str, tags: &[String]) -> Result<()> {
+        let known = Self::BUILTIN_TYPES.iter().any(|b| b.eq_ignore_ascii_case(note_type))
+            || self.note_type(note_type).is_some();
+        if !known {
+            let names: Vec<String> = self.note_types().into_iter().map(|(n, _, _)| n).collect();
+            anyhow::bail!(
+                "invalid-argument: unknown note type '{note_type}' (known: {}; add [notes.types.{note_type}] to columns.toml)",
+                names.join(", ")
+            );
+        }
+        let missing: Vec<&str> = self
+            .note_type(note_type)
+            .map(|t| t.required_tags.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter(|r| !tags.iter().any(|t| t.eq_ignore_ascii_case(r)))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "invalid-argument: {note_type} notes require tags: {}",
+                missing.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    pub fn max_entry_bytes(&self) -> usize {
+        self.max_entry_bytes
+            .filter(|n| *n > 0)
+            .unwrap_or(Self::DEFAULT_MAX_ENTRY_BYTES)
+    }
+
+    pub fn split_oversize(&self) -> bool {
+        self.oversize
+            .as_deref()
+            .is_some_and(|s| s.eq_ignore_ascii_case("split"))
+    }
+}
+
+/// `[workflow]` section: which column moves are allowed and what a card needs to enter a column.
+/// Enforced by kanban_move / kanban_done / kanban_update `toColumn` unless the call passes `force`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WorkflowToml {
+    /// Column -> columns a card in it may move to (`backlog = ["todo", "doing"]`); columns not
+    /// listed here may move anywhere
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub transitions: BTreeMap<String, Vec<String>>,
+    /// Column -> front-matter fields a card must have to enter it (`doing = ["size", "assignees"]`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub require: BTreeMap<String, Vec<String>>,
+}
+
+impl WorkflowToml {
+    /// Why moving a card (`fm`) from `from` to `to` breaks the workflow; None when it is allowed.
+    /// Staying in the same column is always allowed.
+    pub fn violation(&self, from: &str, to: &str, fm: &CardFrontMatter) -> Option<String> {
+        if from.eq_ignore_ascii_case(to) {
+            return None;
+        }
+        if let Some((_, allowed)) = self.transitions.iter().find(|(k, _)| k.eq_ignore_ascii_case(from)) {
+            if !allowed.iter().any(|c| c.eq_ignore_ascii_case(to)) {
+                let allowed = if allowed.is_empty() { "none".to_string() } else { allowed.join(", ") };
+                return Some(format!("{from} -> {to} is not an allowed transition (from {from}: {allowed})"));
+            }
+        }
+        let missing: Vec<&str> = self
+            .require
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(to))
+            .flat_map(|(_, fields)| fields)
+            .filter(|f| !fm.has_field(f))
+            .map(String::as_str)
+            .collect();
+        (!missing.is_empty()).then(|| format!("entering {to} requires {}", missing.join(", ")))
+    }
+}
+
+/// `[labels]` section: label taxonomy
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LabelsToml {
+    /// Allowed label set; labels outside it are accepted but warned about. Unset = any label.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<Vec<String>>,
+}
+
+impl LabelsToml {
+    /// Whether `label` is in the allowed set (always true when no set is configured).
+    pub fn is_allowed(&self, label: &str) -> bool {
+        self.allowed
+            .as_ref()
+            .is_none_or(|a| a.iter().any(|x| x == label))
+    }
+}
+
+/// `[people]` section: assignee directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PeopleToml {
+    /// `warn` (default) | `reject`: what to do with assignees not in the directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_unknown: Option<String>,
+    /// Canonical id -> person (`[people.members.alice]`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub members: BTreeMap<String, PersonToml>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PersonToml {
+    /// Display name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Other spellings that resolve to this person (handles, emails)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+impl PeopleToml {
+    /// Whether a directory is configured (no members = assignees are free-form).
+    pub fn is_configured(&self) -> bool {
+        !self.members.is_empty()
+    }
+
+    pub fn rejects_unknown(&self) -> bool {
+        self.on_unknown
+            .as_deref()
+            .is_some_and(|s| s.eq_ignore_ascii_case("reject"))
+    }
+
+    /// Canonical id for an id or alias (case-insensitive); `None` when nobody matches.
+    pub fn resolve(&self, who: &str) -> Option<&str> {
+        let who = who.trim();
+        self.members
+            .iter()
+            .find(|(id, p)| id.eq_ignore_ascii_case(who) || p.aliases.iter().any(|a| a.eq_ignore_ascii_case(who)))
+            .map(|(id, _)| id.as_str())
+    }
+}
+
+/// `[list]` section: ordering policy for list views
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ListToml {
+    /// Sort keys applied in order, e.g. `["priority", "created_at"]`; prefix `-` for descending.
+    /// Keys: `id` | `created_at` | `priority` | `position` | `title` | `size`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_sort: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    Id,
+    /// ULIDs are time-ordered, so creation order is id order
+    CreatedAt,
+    Priority,
+    Position,
+    Title,
+    Size,
+    UpdatedAt,
+}
+
+/// One sort key (`priority`, `-size`, ...)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub desc: bool,
+}
+
+impl SortKey {
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (desc, name) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let field = match name.to_ascii_lowercase().as_str() {
+            "id" => SortField::Id,
+            "created_at" | "createdat" | "created" => SortField::CreatedAt,
+            "priority" => SortField::Priority,
+            "position" => SortField::Position,
+            "title" => SortField::Title,
+            "size" => SortField::Size,
+            "updated_at" | "updatedat" | "updated" => SortField::UpdatedAt,
+            _ => return None,
+        };
+        Some(Self { field, desc })
+    }
+
+    /// Canonical spelling (`-priority`), as echoed back to clients.
+    pub fn name(&self) -> String {
+        let f = match self.field {
+            SortField::Id => "id",
+            SortField::CreatedAt => "created_at",
+            SortField::Priority => "priority",
+            SortField::Position => "position",
+            SortField::Title => "title",
+            SortField::Size => "size",
+            SortField::UpdatedAt => "updated_at",
+        };
+        if self.desc {
+            format!("-{f}")
+        } else {
+            f.to_string()
+        }
+    }
+}
+
+/// The card fields a [`SortKey`] can look at.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SortFields {
+    pub id: String,
+    pub priority: Option<String>,
+    pub position: Option<i64>,
+    pub title: String,
+    pub size: Option<u32>,
+    pub updated_at: Option<String>,
+}
+
+impl From<&CardFrontMatter> for SortFields {
+    fn from(fm: &CardFrontMatter) -> Self {
+        Self {
+            id: fm.id.to_uppercase(),
+            priority: fm.priority.clone(),
+            position: fm.position,
+            title: fm.title.clone(),
+            size: fm.size,
+            updated_at: fm.updated_at.clone(),
+        }
+    }
+}
+
+/// Compare by `keys` in order, then by id. Missing values sort last in either direction
+/// (priorities compare as text, so `P0` < `P1` < `P2`).
+pub fn compare_by_sort(keys: &[SortKey], a: &SortFields, b: &SortFields) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    fn opt<T: Ord>(a: Option<T>, b: Option<T>, desc: bool) -> Ordering {
+        match (a, b) {
+            (Some(x), Some(y)) if desc => y.cmp(&x),
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+    for k in keys {
+        let ord = match k.field {
+            SortField::Id | SortField::CreatedAt => opt(Some(&a.id), Some(&b.id), k.desc),
+            SortField::Priority => opt(
+                a.priority.as_deref().map(str::to_ascii_lowercase),
+                b.priority.as_deref().map(str::to_ascii_lowercase),
+                k.desc,
+            ),
+            SortField::Position => opt(a.position, b.position, k.desc),
+            SortField::Title => opt(
+                Some(a.title.to_lowercase()),
+                Some(b.title.to_lowercase()),
+                k.desc,
+            ),
+            SortField::Size => opt(a.size, b.size, k.desc),
+            // compared as instants, so mixed offsets order correctly
+            SortField::UpdatedAt => {
+                let at = |s: &Option<String>| s.as_deref().and_then(|s| OffsetDateTime::parse(s, &Rfc3339).ok());
+                opt(at(&a.updated_at), at(&b.updated_at), k.desc)
+            }
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    a.id.cmp(&b.id)
+}
+
+/// Split `text` into chunks of at most `max_bytes`, preferring line boundaries
+/// (a single overlong line is cut at a char boundary).
+pub fn split_note_text(text: &str, max_bytes: usize) -> Vec<String> {
+    let max_bytes = max_bytes.max(4);
+    let mut out: Vec<String> = vec![];
+    let mut cur = String::new();
+    for line in text.split_inclusive('\n') {
+        let mut line = line;
+        while line.len() > max_bytes {
+            let mut cut = max_bytes;
+            while !line.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            if !cur.is_empty() {
+                out.push(std::mem::take(&mut cur));
+            }
+            out.push(line[..cut].to_string());
+            line = &line[cut..];
+        }
+        if cur.len() + line.len() > max_bytes {
+            out.push(std::mem::take(&mut cur));
+        }
+        cur.push_str(line);
+    }
+    if !cur.is_empty() || out.is_empty() {
+        out.push(cur);
+    }
+    out
+}
+
+/// `[terminal.<column>]` section: how cards are filed when they enter a terminal column
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TerminalToml {
+    /// `yyyy-mm` (default) | `yyyy` | `none`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partition: Option<String>,
+    /// Set `completed_at` on entry (default: true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stamp_completed_at: Option<bool>,
+}
+
+/// Directory layout under a terminal column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partition {
+    None,
+    Year,
+    YearMonth,
+}
+
+impl Partition {
+    /// Subdirectory (relative to the column dir) for a card finalized at `at`.
+    pub fn subdir(&self, at: OffsetDateTime) -> std::path::PathBuf {
+        let month: u8 = at.month().into();
+        match self {
+            Partition::None => std::path::PathBuf::new(),
+            Partition::Year => format!("{:04}", at.year()).into(),
+            Partition::YearMonth => std::path::Path::new(&format!("{:04}", at.year())).join(format!("{month:02}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalPolicy {
+    pub partition: Partition,
+    pub stamp_completed_at: bool,
+    /// Board offset: partitions and `completed_at` follow the local calendar
+    pub offset: UtcOffset,
+}
+
+impl TerminalPolicy {
+    /// Partition subdirectory for a card finalized at `at`, by the board's local date.
+    pub fn subdir(&self, at: OffsetDateTime) -> std::path::PathBuf {
+        self.partition.subdir(at.to_offset(self.offset))
+    }
+
+    /// `completed_at` for `at`: RFC3339 with the board's offset.
+    pub fn stamp(&self, at: OffsetDateTime) -> String {
+        at.to_offset(self.offset).format(&Rfc3339).unwrap_or_default()
+    }
+}
+
+/// `[board]` section: identity used in resource URIs instead of filesystem paths
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BoardToml {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// Fixed UTC offset (`+09:00`, `-05:30`, `UTC`) for partitions, date-only bounds and reports
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RenderToml {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debounce_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress_parent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress_parents: Option<Vec<String>>, // 複数親の進捗を出力
+    /// Append a lane×column count table to the simple board render
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lane_matrix: Option<bool>,
+    /// List card titles in each lane×column cell (simple render only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lane_matrix_titles: Option<bool>,
+    /// Mark cards that have sat in their column at least this long (`7d`, `12h`, `2w`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stale_after: Option<String>,
+    /// Per-column overrides of `stale_after`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub stale_after_columns: BTreeMap<String, String>,
+    /// Append a column × time-in-column heatmap to the simple board render
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aging_heatmap: Option<bool>,
+    /// Heatmap bucket boundaries (default `["1d", "3d", "7d"]`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aging_buckets: Option<Vec<String>>,
+    /// Auto-render targets (`[[render.outputs]]`); none means `generated/board.md` alone
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<RenderOutputToml>,
+}
+
+/// One `[[render.outputs]]` entry: a file under `.kanban/generated/` rendered on every flush.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RenderOutputToml {
+    /// Path under `.kanban/generated/`; `{lane}` renders one file per lane
+    pub path: String,
+    /// Handlebars template in `.kanban/templates/` (omitted: the built-in board render)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Scope: only cards in these lanes (empty: all)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lanes: Vec<String>,
+    /// Scope: only cards carrying any of these labels (empty: all)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    /// Scope: only cards in these columns (empty: all)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+}
+
+impl RenderToml {
+    /// Staleness threshold for `column` (per-column override, then `stale_after`).
+    pub fn stale_threshold(&self, column: &str) -> Option<time::Duration> {
+        self.stale_after_columns
+            .get(column)
+            .or(self.stale_after.as_ref())
+            .and_then(|s| parse_age(s))
+    }
+
+    /// Ascending heatmap boundaries as (label, age); unparsable entries are dropped and an
+    /// empty result falls back to `1d`/`3d`/`7d`.
+    pub fn aging_bounds(&self) -> Vec<(String, time::Duration)> {
+        let mut bounds: Vec<(String, time::Duration)> = self
+            .aging_buckets
+            .iter()
+            .flatten()
+            .filter_map(|s| Some((s.trim().to_string(), parse_age(s)?)))
+            .collect();
+        if bounds.is_empty() {
+            bounds = ["1d", "3d", "7d"]
+                .iter()
+                .map(|s| (s.to_string(), parse_age(s).unwrap()))
+                .collect();
+        }
+        bounds.sort_by_key(|(_, d)| *d);
+        bounds.dedup_by_key(|(_, d)| *d);
+        bounds
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RemindersToml {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_hours: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_ms: Option<u64>,
+}
+
+/// Server-side governor for tool calls (per board)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LimitsToml {
+    /// Max concurrent mutating calls per board (default 1 = serialized writes)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_writes: Option<usize>,
+    /// Per-tool call caps per rolling minute, e.g. `kanban_new = 30`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub per_minute: BTreeMap<String, u32>,
+    /// Cap on one tool result in serialized JSON bytes (overrides the server default; 0 = no cap)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<usize>,
+    /// Reject arguments beyond the input schema's bounds instead of clamping them (default false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// One journal entry (NDJSON per card)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NoteEntry {
+    pub ts: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Structured fields for `type:"decision"` (text holds the rendered block)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision: Option<DecisionRecord>,
+    /// Client-supplied dedup key; an append with a key already present for the card is skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// One-line preview (first non-empty line of `text`), filled in on append
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+impl NoteEntry {
+    pub const SUMMARY_MAX_CHARS: usize = 100;
+
+    /// First non-empty line without leading markdown markers (`#`, `-`, `*`, `>`), cut to 100 chars.
+    pub fn summary_of(text: &str) -> String {
+        let line = text
+            .lines()
+            .map(|l| l.trim().trim_start_matches(['#', '-', '*', '>']).trim())
+            .find(|l| !l.is_empty())
+            .unwrap_or("");
+        if line.chars().count() > Self::SUMMARY_MAX_CHARS {
+            let mut s: String = line.chars().take(Self::SUMMARY_MAX_CHARS - 1).collect();
+            s.push('…');
+            s
+        } else {
+            line.to_string()
+        }
+    }
+}
+
+/// One audit event of a card (NDJSON per card under `.kanban/history/`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct HistoryEvent {
+    pub ts: String,
+    /// Tool that made the change (`kanban_move`, `kanban_bulk`, ...)
+    pub tool: String,
+    /// `create` | `update` | `delete` | `restore`
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    /// Column after the change (the last one for `delete`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<FieldChange>,
+}
+
+/// Changed field of a history event; `body` changes carry no values.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<serde_json::Value>,
+}
+
+impl FieldChange {
+    pub const VALUE_MAX_CHARS: usize = 120;
+
+    /// Front-matter fields whose value differs, in field-name order (`updated_at`, which every
+    /// write changes, is left out). Strings longer than `VALUE_MAX_CHARS` are cut so a rewritten
+    /// description does not bloat the history.
+    pub fn diff(before: &CardFrontMatter, after: &CardFrontMatter) -> Vec<FieldChange> {
+        let as_map = |fm: &CardFrontMatter| match serde_json::to_value(fm) {
+            Ok(serde_json::Value::Object(m)) => m,
+            _ => serde_json::Map::new(),
+        };
+        let (a, b) = (as_map(before), as_map(after));
+        let fields: std::collections::BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+        let cut = |v: Option<&serde_json::Value>| {
+            v.filter(|v| !v.is_null()).map(|v| match v.as_str() {
+                Some(s) if s.chars().count() > Self::VALUE_MAX_CHARS => {
+                    let mut s: String = s.chars().take(Self::VALUE_MAX_CHARS - 1).collect();
+                    s.push('…');
+                    serde_json::Value::String(s)
+                }
+                _ => v.clone(),
+            })
+        };
+        fields
+            .into_iter()
+            .filter(|f| f.as_str() != "updated_at" && a.get(*f) != b.get(*f))
+            .map(|f| FieldChange { field: f.clone(), from: cut(a.get(f)), to: cut(b.get(f)) })
+            .collect()
+    }
+}
+
+/// One undoable call in `.kanban/operations.ndjson`: every card it changed, before and after.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Operation {
+    pub ts: String,
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    pub cards: Vec<OperationCard>,
+}
+
+/// A card touched by an operation; `before` is absent for created cards, `after` for deleted ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct OperationCard {
+    pub id: String,
+    /// History action recorded for the card (`create`, `update`, `delete`, `restore`); undo
+    /// trashes a card only when the operation created or restored it
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<CardSnapshot>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<CardSnapshot>,
+}
+
+/// A card file as it was: column, path relative to the board root, and full markdown.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CardSnapshot {
+    pub column: String,
+    pub path: String,
+    pub markdown: String,
+}
+
+/// Architecture decision record attached to a decision note
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DecisionRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consequences: Option<String>,
+}
+
+impl DecisionRecord {
+    pub fn is_empty(&self) -> bool {
+        self.context.is_none()
+            && self.options.is_empty()
+            && self.decision.is_none()
+            && self.consequences.is_none()
+    }
+
+    /// Render as a fixed-order markdown block, headed by `summary` when non-empty.
+    pub fn to_markdown(&self, summary: &str) -> String {
+        let mut out = String::new();
+        if !summary.trim().is_empty() {
+            out.push_str(&format!("### {}\n\n", summary.trim()));
+        }
+        if let Some(c) = &self.context {
+            out.push_str(&format!("**Context**\n\n{}\n\n", c.trim()));
+        }
+        if !self.options.is_empty() {
+            out.push_str("**Options considered**\n\n");
+            for o in &self.options {
+                out.push_str(&format!("- {}\n", o.trim()));
+            }
+            out.push('\n');
+        }
+        if let Some(d) = &self.decision {
+            out.push_str(&format!("**Decision**\n\n{}\n\n", d.trim()));
+        }
+        if let Some(c) = &self.consequences {
+            out.push_str(&format!("**Consequences**\n\n{}\n\n", c.trim()));
+        }
+        out.trim_end().to_string()
+    }
 }
@@ -13,6 +13,369 @@ fn count_files_in(dir: &std::path::Path) -> usize {
         .count()
 }
 
+/// Lane×column matrix: `{ columns: [..], rows: [{ lane, total, cells: [{ column, count, titles }] }] }`.
+/// Cards without a lane are grouped under `(none)`; `columns` are the board columns plus the
+/// done-like ones (`done` and `[terminal.*]`).
+/// Titles follow `[list] default_sort` when configured, else alphabetical order.
+pub fn lane_matrix(board: &Board, columns: &[String]) -> serde_json::Value {
+    use kanban_model::{CardFile, SortFields};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+    let sort = kanban_model::ColumnsToml::load(&board.root).configured_sort();
+    let done_like = board.done_like();
+    let base = board.root.join(".kanban");
+    let mut cols: Vec<String> = columns.to_vec();
+    for d in done_like.columns() {
+        if !cols.iter().any(|c| c.eq_ignore_ascii_case(d)) {
+            cols.push(d.clone());
+        }
+    }
+    // lane -> column -> cards
+    let mut grid: BTreeMap<String, BTreeMap<String, Vec<SortFields>>> = BTreeMap::new();
+    for c in &cols {
+        let dir = base.join(c);
+        if !dir.exists() {
+            continue;
+        }
+        // 終端列は done/YYYY/MM/ 配下まで、それ以外は列直下のみ
+        let max_depth = done_like.scan_depth(c);
+        for e in walkdir::WalkDir::new(&dir)
+            .min_depth(1)
+            .max_depth(max_depth)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            if let Ok(card) = fs_err::read_to_string(e.path())
+                .map_err(anyhow::Error::from)
+                .and_then(|t| CardFile::from_markdown(&t))
+            {
+                let lane = card
+                    .front_matter
+                    .lane
+                    .clone()
+                    .filter(|l| !l.trim().is_empty())
+                    .unwrap_or_else(|| "(none)".into());
+                grid.entry(lane)
+                    .or_default()
+                    .entry(c.clone())
+                    .or_default()
+                    .push(SortFields::from(&card.front_matter));
+            }
+        }
+    }
+    let rows: Vec<serde_json::Value> = grid
+        .into_iter()
+        .map(|(lane, by_col)| {
+            let cells: Vec<serde_json::Value> = cols
+                .iter()
+                .map(|c| {
+                    let mut cards = by_col.get(c).cloned().unwrap_or_default();
+                    match sort.as_deref() {
+                        Some(keys) => cards.sort_by(|a, b| kanban_model::compare_by_sort(keys, a, b)),
+                        None => cards.sort_by(|a, b| a.title.cmp(&b.title)),
+                    }
+                    let titles: Vec<String> = cards.into_iter().map(|c| c.title).collect();
+                    json!({"column": c, "count": titles.len(), "titles": titles})
+                })
+                .collect();
+            let total: usize = by_col.values().map(|v| v.len()).sum();
+            json!({"lane": lane, "total": total, "cells": cells})
+        })
+        .collect();
+    json!({"columns": cols, "rows": rows})
+}
+
+/// Cards carrying front-matter `links`, in `[list] default_sort` order (id order when unset):
+/// `[{id, title, links:[{title,url}]}]`.
+pub fn card_links(board: &Board) -> serde_json::Value {
+    use kanban_model::{CardFile, SortFields};
+    let sort = kanban_model::ColumnsToml::load(&board.root).default_sort();
+    let root = board.root.join(".kanban");
+    let mut out: Vec<(SortFields, serde_json::Value)> = vec![];
+    if root.exists() {
+        for e in walkdir::WalkDir::new(&root)
+            .into_iter()
+            .filter_entry(kanban_storage::is_card_entry)
+            .filter_map(|e| e.ok())
+        {
+            let p = e.path();
+            if !e.file_type().is_file()
+                || !p
+                    .extension()
+                    .and_then(|s| s.to_str())
+                    .map(|s| s.eq_ignore_ascii_case("md"))
+                    .unwrap_or(false)
+            {
+                continue;
+            }
+            let Ok(text) = fs_err::read_to_string(p) else {
+                continue;
+            };
+            let Ok(card) = CardFile::from_markdown(&text) else {
+                continue;
+            };
+            let fm = card.front_matter;
+            if let Some(links) = fm.links.as_ref().filter(|l| !l.is_empty()) {
+                let id = fm.id.to_uppercase();
+                out.push((
+                    SortFields::from(&fm),
+                    serde_json::json!({"id": id, "title": fm.title, "links": links}),
+                ));
+            }
+        }
+    }
+    out.sort_by(|a, b| kanban_model::compare_by_sort(&sort, &a.0, &b.0));
+    serde_json::Value::Array(out.into_iter().map(|(_, v)| v).collect())
+}
+
+/// Marker placed on cards past their column's `[render] stale_after` threshold
+pub const STALE_MARK: &str = "⚠";
+
+/// Per-column card ages: `[{ column, oldestAgeDays, staleCount, cards: [{ id, title, ageDays, ageSeconds, stale, mark }] }]`.
+/// Age counts from `in_column_since` in cards.ndjson (file mtime when the index has no entry for
+/// the column); cards follow `[list] default_sort`. `stale`/`mark` use `[render] stale_after`.
+pub fn column_ages(board: &Board, columns: &[String]) -> serde_json::Value {
+    use kanban_model::{CardFile, SortFields};
+    use serde_json::json;
+    use time::format_description::well_known::Rfc3339;
+    let cfg = kanban_model::ColumnsToml::load(&board.root);
+    let sort = cfg.default_sort();
+    let base = board.root.join(".kanban");
+    let mut since: std::collections::HashMap<(String, String), time::OffsetDateTime> = Default::default();
+    if let Ok(text) = fs_err::read_to_string(base.join("cards.ndjson")) {
+        for v in text.lines().filter_map(|l| serde_json::from_str::<serde_json::Value>(l).ok()) {
+            if let (Some(id), Some(col), Some(t)) = (
+                v["id"].as_str(),
+                v["column"].as_str(),
+                v["in_column_since"].as_str().and_then(|s| time::OffsetDateTime::parse(s, &Rfc3339).ok()),
+            ) {
+                since.insert((id.to_uppercase(), col.to_string()), t);
+            }
+        }
+    }
+    let now = kanban_model::now();
+    let out: Vec<serde_json::Value> = columns
+        .iter()
+        .map(|c| {
+            let threshold = cfg.render.stale_threshold(c);
+            let mut cards: Vec<(SortFields, time::Duration)> = vec![];
+            for e in walkdir::WalkDir::new(base.join(c))
+                .min_depth(1)
+                .max_depth(1)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                let Ok(card) = fs_err::read_to_string(e.path())
+                    .map_err(anyhow::Error::from)
+                    .and_then(|t| CardFile::from_markdown(&t))
+                else {
+                    continue;
+                };
+                let entered = since
+                    .get(&(card.front_matter.id.to_uppercase(), c.clone()))
+                    .copied()
+                    .or_else(|| {
+                        let m = e.metadata().ok()?.modified().ok()?;
+                        Some(time::OffsetDateTime::from(m))
+                    })
+                    .unwrap_or(now);
+                cards.push((SortFields::from(&card.front_matter), now - entered));
+            }
+            cards.sort_by(|a, b| kanban_model::compare_by_sort(&sort, &a.0, &b.0));
+            let oldest = cards.iter().map(|(_, age)| age.whole_days()).max();
+            let is_stale = |age: &time::Duration| threshold.is_some_and(|t| *age >= t);
+            let stale_count = cards.iter().filter(|(_, age)| is_stale(age)).count();
+            let cards: Vec<serde_json::Value> = cards
+                .iter()
+                .map(|(f, age)| {
+                    let stale = is_stale(age);
+                    json!({
+                        "id": f.id.to_uppercase(),
+                        "title": f.title,
+                        "ageDays": age.whole_days(),
+                        "ageSeconds": age.whole_seconds(),
+                        "stale": stale,
+                        "mark": if stale { STALE_MARK } else { "" },
+                    })
+                })
+                .collect();
+            json!({"column": c, "oldestAgeDays": oldest, "staleCount": stale_count, "cards": cards})
+        })
+        .collect();
+    serde_json::Value::Array(out)
+}
+
+/// Heatmap bucket labels for ascending boundaries: `<1d`, `1–3d`, `3–7d`, `>7d`.
+fn aging_bucket_labels(bounds: &[(String, time::Duration)]) -> Vec<String> {
+    let Some((first, _)) = bounds.first() else {
+        return vec!["all".into()];
+    };
+    let mut labels = vec![format!("<{first}")];
+    for w in bounds.windows(2) {
+        let (a, b) = (&w[0].0, &w[1].0);
+        // 単位が同じなら左側の単位を省きます（1d–3d → 1–3d）
+        let unit = |s: &str| s.trim_start_matches(|c: char| c.is_ascii_digit()).to_string();
+        let a = if unit(a) == unit(b) { a.trim_end_matches(|c: char| !c.is_ascii_digit()) } else { a.as_str() };
+        labels.push(format!("{a}–{b}"));
+    }
+    labels.push(format!(">{}", bounds[bounds.len() - 1].0));
+    labels
+}
+
+/// Cards per column bucketed by time in column: `{ buckets: [label], max, rows: [{ column, total,
+/// cells: [{ bucket, count }] }] }`. Boundaries come from `[render] aging_buckets`.
+pub fn aging_heatmap(board: &Board, columns: &[String]) -> serde_json::Value {
+    use serde_json::json;
+    let bounds = kanban_model::ColumnsToml::load(&board.root).render.aging_bounds();
+    let labels = aging_bucket_labels(&bounds);
+    let ages = column_ages(board, columns);
+    let mut max = 0;
+    let rows: Vec<serde_json::Value> = ages
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|col| {
+            let mut counts = vec![0usize; labels.len()];
+            for c in col["cards"].as_array().into_iter().flatten() {
+                let age = time::Duration::seconds(c["ageSeconds"].as_i64().unwrap_or(0));
+                let i = bounds.iter().position(|(_, b)| age < *b).unwrap_or(bounds.len());
+                counts[i] += 1;
+            }
+            max = max.max(counts.iter().copied().max().unwrap_or(0));
+            let cells: Vec<serde_json::Value> = labels
+                .iter()
+                .zip(&counts)
+                .map(|(l, n)| json!({"bucket": l, "count": n}))
+                .collect();
+            json!({"column": col["column"], "total": counts.iter().sum::<usize>(), "cells": cells})
+        })
+        .collect();
+    json!({"buckets": labels, "max": max, "rows": rows})
+}
+
+/// Cell shades from sparse to full, relative to the fullest cell of the heatmap.
+const HEAT_SHADES: [&str; 4] = ["░", "▒", "▓", "█"];
+
+fn render_aging_heatmap_markdown(heatmap: &serde_json::Value) -> String {
+    let buckets: Vec<&str> = heatmap["buckets"]
+        .as_array()
+        .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    let max = heatmap["max"].as_u64().unwrap_or(0).max(1);
+    let mut out = String::from("\n## Aging\n\n");
+    out.push_str(&format!("| column | {} |\n", buckets.join(" | ")));
+    out.push_str(&format!("|---|{}\n", "---|".repeat(buckets.len())));
+    for row in heatmap["rows"].as_array().into_iter().flatten() {
+        let cells: Vec<String> = row["cells"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|cell| match cell["count"].as_u64().unwrap_or(0) {
+                0 => "·".to_string(),
+                n => format!("{} {n}", HEAT_SHADES[((n * 4).div_ceil(max) as usize).clamp(1, 4) - 1]),
+            })
+            .collect();
+        out.push_str(&format!(
+            "| {} | {} |\n",
+            row["column"].as_str().unwrap_or(""),
+            cells.join(" | ")
+        ));
+    }
+    out
+}
+
+fn render_stale_markdown(ages: &serde_json::Value) -> String {
+    let stale: Vec<String> = ages
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|col| {
+            let column = col["column"].as_str().unwrap_or_default().to_string();
+            col["cards"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|c| c["stale"].as_bool().unwrap_or(false))
+                .map(move |c| {
+                    format!(
+                        "- {STALE_MARK} {} ({}): {column}, {}d\n",
+                        c["title"].as_str().unwrap_or_default(),
+                        c["id"].as_str().unwrap_or_default(),
+                        c["ageDays"].as_i64().unwrap_or(0)
+                    )
+                })
+        })
+        .collect();
+    if stale.is_empty() {
+        return String::new();
+    }
+    format!("\n## Stale\n\n{}", stale.concat())
+}
+
+fn render_card_links_markdown(cards: &serde_json::Value) -> String {
+    let Some(cards) = cards.as_array().filter(|c| !c.is_empty()) else {
+        return String::new();
+    };
+    let mut out = String::from("\n## Links\n\n");
+    for c in cards {
+        let links: Vec<String> = c["links"]
+            .as_array()
+            .map(|a| {
+                a.iter()
+                    .map(|l| {
+                        let url = l["url"].as_str().unwrap_or_default();
+                        format!("[{}]({url})", l["title"].as_str().unwrap_or(url))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "- {} ({}): {}\n",
+            c["title"].as_str().unwrap_or_default(),
+            c["id"].as_str().unwrap_or_default(),
+            links.join(", ")
+        ));
+    }
+    out
+}
+
+fn render_lane_matrix_markdown(matrix: &serde_json::Value, with_titles: bool) -> String {
+    let cols: Vec<&str> = matrix["columns"]
+        .as_array()
+        .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    let mut out = String::from("\n## Lanes\n\n");
+    out.push_str(&format!("| lane | {} |\n", cols.join(" | ")));
+    out.push_str(&format!("|---|{}\n", "---|".repeat(cols.len())));
+    for row in matrix["rows"].as_array().into_iter().flatten() {
+        let cells: Vec<String> = row["cells"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|cell| {
+                let n = cell["count"].as_u64().unwrap_or(0);
+                let titles: Vec<&str> = cell["titles"]
+                    .as_array()
+                    .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+                    .unwrap_or_default();
+                if with_titles && !titles.is_empty() {
+                    format!("{n}<br>{}", titles.join("<br>").replace('|', "\\|"))
+                } else {
+                    n.to_string()
+                }
+            })
+            .collect();
+        out.push_str(&format!(
+            "| {} | {} |\n",
+            row["lane"].as_str().unwrap_or(""),
+            cells.join(" | ")
+        ));
+    }
+    out
+}
+
 pub fn render_simple_board(board: &Board) -> Result<String> {
     let base = board.root.join(".kanban");
     // columns from columns.toml or fallback
@@ -29,27 +392,218 @@ pub fn render_simple_board(board: &Board) -> Result<String> {
     } else {
         cols_cfg.columns.clone()
     };
-    // ensure stable order and dedup
+    // ensure stable order and dedup; done-like columns are listed after the open ones
     cols.dedup();
+    let done_like = board.done_like();
+    cols.retain(|c| !done_like.contains(c));
+    let ages = column_ages(board, &cols);
     let mut out = String::new();
     out.push_str(
         "# Board
 
 ",
     );
-    for c in &cols {
+    for (c, age) in cols.iter().zip(ages.as_array().into_iter().flatten()) {
         let n = count_files_in(&base.join(c));
+        let oldest = match age["oldestAgeDays"].as_i64() {
+            Some(d) => format!(" (oldest {d}d)"),
+            None => String::new(),
+        };
         out.push_str(&format!(
-            "- {c}: {n}
+            "- {c}: {n}{oldest}
 "
         ));
     }
-    let done = count_files_in(&base.join("done"));
-    out.push_str(&format!("- done: {done}\n"));
+    for d in done_like.columns() {
+        let n = count_files_in(&base.join(d));
+        if n > 0 || d == "done" {
+            out.push_str(&format!("- {d}: {n}\n"));
+        }
+    }
+    if cols_cfg.render.lane_matrix.unwrap_or(false) {
+        let matrix = lane_matrix(board, &cols);
+        out.push_str(&render_lane_matrix_markdown(
+            &matrix,
+            cols_cfg.render.lane_matrix_titles.unwrap_or(false),
+        ));
+    }
+    if cols_cfg.render.aging_heatmap.unwrap_or(false) {
+        out.push_str(&render_aging_heatmap_markdown(&aging_heatmap(board, &cols)));
+    }
+    out.push_str(&render_stale_markdown(&ages));
+    out.push_str(&render_card_links_markdown(&card_links(board)));
     Ok(out)
 }
 
+/// Expand `{{title}}`-style placeholders in a new card's body with `vars`. The body is Markdown, so
+/// nothing is HTML-escaped; a placeholder missing from `vars` is an error rather than an empty string.
+pub fn render_card_body(template_text: &str, vars: &serde_json::Value) -> Result<String> {
+    let mut hb = handlebars::Handlebars::new();
+    hb.set_strict_mode(true);
+    hb.register_escape_fn(handlebars::no_escape);
+    Ok(hb.render_template(template_text, vars)?)
+}
+
+/// The board view written to `generated/board.md`: `.kanban/templates/board.hbs` (or
+/// `board.md.hbs`) when present, else the simple render.
+pub fn render_board(board: &Board) -> Result<String> {
+    let dir = board.root.join(".kanban").join("templates");
+    match ["board.hbs", "board.md.hbs"].iter().map(|n| dir.join(n)).find(|p| p.exists()) {
+        Some(path) => render_board_with_template(board, &fs_err::read_to_string(path)?),
+        None => render_simple_board(board),
+    }
+}
+
+/// Which cards a scoped render covers. Each non-empty list must match (labels: any of them);
+/// comparisons ignore case.
+#[derive(Debug, Clone, Default)]
+pub struct RenderScope {
+    pub lanes: Vec<String>,
+    pub labels: Vec<String>,
+    pub columns: Vec<String>,
+}
+
+impl RenderScope {
+    pub fn of(output: &kanban_model::RenderOutputToml) -> Self {
+        Self {
+            lanes: output.lanes.clone(),
+            labels: output.labels.clone(),
+            columns: output.columns.clone(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.is_empty() && self.labels.is_empty() && self.columns.is_empty()
+    }
+
+    pub fn admits(&self, column: &str, fm: &kanban_model::CardFrontMatter) -> bool {
+        let any = |list: &[String], v: &str| list.iter().any(|x| x.eq_ignore_ascii_case(v));
+        (self.columns.is_empty() || any(&self.columns, column))
+            && (self.lanes.is_empty() || fm.lane.as_deref().is_some_and(|l| any(&self.lanes, l)))
+            && (self.labels.is_empty()
+                || fm.labels.iter().flatten().any(|l| any(&self.labels, l)))
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "lanes": self.lanes,
+            "labels": self.labels,
+            "columns": self.columns,
+            "lane": (self.lanes.len() == 1).then(|| self.lanes[0].clone()),
+        })
+    }
+}
+
+/// Cards admitted by `scope` as (column, card), in board column order (done-like columns
+/// last), then position (cards without one last), then id.
+fn scoped_cards(board: &Board, scope: &RenderScope) -> Vec<(String, kanban_model::CardFile)> {
+    let cfg = kanban_model::ColumnsToml::load(&board.root);
+    let done_like = kanban_storage::DoneLike::new(&cfg);
+    let rank = |c: &str| {
+        let open = cfg.columns.iter().position(|x| x.eq_ignore_ascii_case(c));
+        (done_like.contains(c), open.unwrap_or(usize::MAX), c.to_string())
+    };
+    let mut cards: Vec<(String, kanban_model::CardFile)> = board
+        .card_files()
+        .filter_map(|(p, column)| {
+            let card = kanban_model::CardFile::from_markdown(&fs_err::read_to_string(&p).ok()?).ok()?;
+            scope.admits(&column, &card.front_matter).then_some((column, card))
+        })
+        .collect();
+    cards.sort_by(|(ca, a), (cb, b)| {
+        rank(ca)
+            .cmp(&rank(cb))
+            .then_with(|| {
+                let pos = |c: &kanban_model::CardFile| c.front_matter.position.unwrap_or(i64::MAX);
+                pos(a).cmp(&pos(b))
+            })
+            .then_with(|| a.front_matter.id.cmp(&b.front_matter.id))
+    });
+    cards
+}
+
+/// The built-in view of a scoped output: one section per column with the admitted cards.
+pub fn render_scoped_board(board: &Board, scope: &RenderScope) -> Result<String> {
+    let mut parts = vec![];
+    for (k, v) in [("lane", &scope.lanes), ("label", &scope.labels), ("column", &scope.columns)] {
+        if !v.is_empty() {
+            parts.push(format!("{k}: {}", v.join(", ")));
+        }
+    }
+    let mut out = if parts.is_empty() {
+        "# Board\n".to_string()
+    } else {
+        format!("# Board ({})\n", parts.join("; "))
+    };
+    for group in scoped_cards(board, scope).chunk_by(|a, b| a.0 == b.0) {
+        out.push_str(&format!("\n## {} ({})\n", group[0].0, group.len()));
+        for (_, card) in group {
+            out.push_str(&format!("- {} ({})\n", card.front_matter.title, card.front_matter.id));
+        }
+    }
+    Ok(out)
+}
+
+/// Files of one `[[render.outputs]]` entry as (path under `.kanban/generated/`, content).
+/// A `{lane}` path renders once per lane found among the scoped cards.
+pub fn render_output(
+    board: &Board,
+    output: &kanban_model::RenderOutputToml,
+) -> Result<Vec<(String, String)>> {
+    let rel = std::path::Path::new(&output.path);
+    if output.path.trim().is_empty()
+        || rel.is_absolute()
+        || rel.components().any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        anyhow::bail!("invalid-argument: render output path {:?} must be relative to .kanban/generated/", output.path);
+    }
+    let template = match &output.template {
+        Some(name) => {
+            let path = board.root.join(".kanban").join("templates").join(name);
+            Some(fs_err::read_to_string(path)?)
+        }
+        None => None,
+    };
+    let scope = RenderScope::of(output);
+    let render = |scope: &RenderScope| match &template {
+        Some(tpl) => render_board_with_template_scoped(board, tpl, scope),
+        None if scope.is_empty() => render_board(board),
+        None => render_scoped_board(board, scope),
+    };
+    if !output.path.contains("{lane}") {
+        return Ok(vec![(output.path.clone(), render(&scope)?)]);
+    }
+    let mut lanes: Vec<String> = scoped_cards(board, &scope)
+        .into_iter()
+        .filter_map(|(_, c)| c.front_matter.lane.filter(|l| !l.trim().is_empty()))
+        .collect();
+    lanes.sort();
+    lanes.dedup();
+    lanes
+        .into_iter()
+        .map(|lane| {
+            let mut slug = kanban_model::fuzzy_tokens(&lane).join("-");
+            if slug.is_empty() {
+                slug = "lane".into();
+            }
+            let per_lane = RenderScope { lanes: vec![lane], ..scope.clone() };
+            Ok((output.path.replace("{lane}", &slug), render(&per_lane)?))
+        })
+        .collect()
+}
+
 pub fn render_board_with_template(board: &Board, template_text: &str) -> Result<String> {
+    render_board_with_template_scoped(board, template_text, &RenderScope::default())
+}
+
+/// `render_board_with_template` plus `scope` and the admitted `cards[]`
+/// (`{id,title,column,lane,labels,assignees,priority,size,due_date}`) in the context. Counts
+/// and the other board-wide keys are not narrowed.
+pub fn render_board_with_template_scoped(
+    board: &Board,
+    template_text: &str,
+    scope: &RenderScope,
+) -> Result<String> {
     use serde_json::json;
     let base = board.root.join(".kanban");
     let cols_cfg = {
@@ -60,19 +614,27 @@ pub fn render_board_with_template(board: &Board, template_text: &str) -> Result<
             kanban_model::ColumnsToml::default()
         }
     };
-    let cols = if cols_cfg.columns.is_empty() {
+    let done_like = kanban_storage::DoneLike::new(&cols_cfg);
+    let cols: Vec<String> = if cols_cfg.columns.is_empty() {
         vec!["backlog".into(), "doing".into(), "review".into()]
     } else {
-        cols_cfg.columns.clone()
+        cols_cfg.columns.iter().filter(|c| !done_like.contains(c)).cloned().collect()
     };
     let mut items = Vec::new();
     let mut non_done: usize = 0;
-    for c in &cols {
+    let ages = column_ages(board, &cols);
+    for (c, age) in cols.iter().zip(ages.as_array().into_iter().flatten()) {
         let n = count_files_in(&base.join(c));
         non_done += n;
-        items.push(json!({"key": c, "count": n}));
+        items.push(json!({
+            "key": c,
+            "count": n,
+            "oldestAgeDays": age["oldestAgeDays"],
+            "staleCount": age["staleCount"],
+            "cards": age["cards"],
+        }));
     }
-    let done = count_files_in(&base.join("done"));
+    let done: usize = done_like.columns().iter().map(|d| count_files_in(&base.join(d))).sum();
     let total = non_done + done;
     let done_rate = if total > 0 {
         (done as f64) / (total as f64)
@@ -81,74 +643,6 @@ pub fn render_board_with_template(board: &Board, template_text: &str) -> Result<
     };
     // Build progressParents (if configured)
     let mut progress_parents: Vec<serde_json::Value> = Vec::new();
-    // Scan once for title map and by_parent
-    use kanban_model::CardFile;
-    let root = board.root.join(".kanban");
-    let mut by_parent: std::collections::HashMap<String, Vec<CardFile>> =
-        std::collections::HashMap::new();
-    let mut title_map: std::collections::HashMap<String, (String, String)> =
-        std::collections::HashMap::new();
-    if root.exists() {
-        for e in walkdir::WalkDir::new(&root)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            if e.file_type().is_file() {
-                let p = e.path();
-                if !p
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.eq_ignore_ascii_case("md"))
-                    .unwrap_or(false)
-                {
-                    continue;
-                }
-                if let Ok(text) = fs_err::read_to_string(p) {
-                    if let Ok(card) = CardFile::from_markdown(&text) {
-                        title_map.insert(
-                            card.front_matter.id.to_uppercase(),
-                            (card.front_matter.title.clone(), String::new()),
-                        );
-                        if let Some(parent) = card.front_matter.parent.as_deref() {
-                            by_parent
-                                .entry(parent.to_uppercase())
-                                .or_default()
-                                .push(card);
-                        }
-                    }
-                }
-            }
-        }
-    }
-    fn dfs(
-        id: &str,
-        by_parent: &std::collections::HashMap<String, Vec<CardFile>>,
-    ) -> (u32, u32, u32, u32) {
-        let mut done = 0;
-        let mut total = 0;
-        let mut done_size = 0;
-        let mut total_size = 0;
-        if let Some(ch) = by_parent.get(&id.to_uppercase()) {
-            for c in ch {
-                total += 1;
-                if let Some(sz) = c.front_matter.size {
-                    total_size += sz;
-                }
-                if c.front_matter.completed_at.is_some() {
-                    done += 1;
-                    if let Some(sz) = c.front_matter.size {
-                        done_size += sz;
-                    }
-                }
-                let (cd, ct, cds, cts) = dfs(&c.front_matter.id, by_parent);
-                done += cd;
-                total += ct;
-                done_size += cds;
-                total_size += cts;
-            }
-        }
-        (done, total, done_size, total_size)
-    }
     let parents_cfg: Vec<String> = if let Some(list) = cols_cfg.render.progress_parents.clone() {
         list
     } else if let Some(one) = cols_cfg.render.progress_parent.clone() {
@@ -158,11 +652,11 @@ pub fn render_board_with_template(board: &Board, template_text: &str) -> Result<
     };
     for pid in parents_cfg {
         let up = pid.to_uppercase();
-        let (title, _col) = title_map
-            .get(&up)
-            .cloned()
-            .unwrap_or((String::new(), String::new()));
-        let (d, t, ds, ts) = dfs(&up, &by_parent);
+        let title = board
+            .read_card(&up)
+            .map(|c| c.front_matter.title)
+            .unwrap_or_default();
+        let (d, t, ds, ts) = board.rollup_count_size(&up)?;
         let percent = if t > 0 {
             (d as f64) / (t as f64) * 100.0
         } else {
@@ -185,77 +679,68 @@ pub fn render_board_with_template(board: &Board, template_text: &str) -> Result<
         }));
     }
     let ctx = json!({"columns": items, "done": done, "nonDone": non_done, "total": total, "doneRate": done_rate});
-    let hb = handlebars::Handlebars::new();
+    let mut hb = handlebars::Handlebars::new();
+    // {{sizeLabel n}} -> scale name from [sizes] (falls back to the number)
+    let scale = cols_cfg.clone();
+    hb.register_helper(
+        "sizeLabel",
+        Box::new(
+            move |h: &handlebars::Helper,
+                  _: &handlebars::Handlebars,
+                  _: &handlebars::Context,
+                  _: &mut handlebars::RenderContext,
+                  out: &mut dyn handlebars::Output|
+                  -> handlebars::HelperResult {
+                let v = h.param(0).map(|p| p.value().clone()).unwrap_or_default();
+                let text = match v.as_u64() {
+                    Some(n) => scale.size_label(n as u32).unwrap_or_else(|| n.to_string()),
+                    None => v.as_str().unwrap_or_default().to_string(),
+                };
+                out.write(&text)?;
+                Ok(())
+            },
+        ),
+    );
     // enrich context
     let mut ctx_obj = ctx.as_object().cloned().unwrap_or_default();
     ctx_obj.insert("progressParents".into(), json!(progress_parents));
+    ctx_obj.insert("laneMatrix".into(), lane_matrix(board, &cols));
+    ctx_obj.insert("agingHeatmap".into(), aging_heatmap(board, &cols));
+    ctx_obj.insert("cardLinks".into(), card_links(board));
+    ctx_obj.insert("scope".into(), scope.to_json());
+    ctx_obj.insert(
+        "cards".into(),
+        json!(scoped_cards(board, scope)
+            .into_iter()
+            .map(|(column, c)| {
+                let fm = c.front_matter;
+                json!({
+                    "id": fm.id,
+                    "title": fm.title,
+                    "column": column,
+                    "lane": fm.lane,
+                    "labels": fm.labels.unwrap_or_default(),
+                    "assignees": fm.assignees.unwrap_or_default(),
+                    "priority": fm.priority,
+                    "size": fm.size,
+                    "due_date": fm.due_date,
+                })
+            })
+            .collect::<Vec<_>>()),
+    );
+    ctx_obj.insert(
+        "sizeScale".into(),
+        json!(cols_cfg
+            .sizes
+            .iter()
+            .map(|(k, v)| json!({"name": k, "weight": v}))
+            .collect::<Vec<_>>()),
+    );
     Ok(hb.render_template(template_text, &serde_json::Value::Object(ctx_obj))?)
 }
 
 pub fn render_parent_progress(board: &Board, parent_id: &str) -> Result<String> {
-    // minimal rollup: count children (direct + transitive) and size sums
-    use kanban_model::CardFile;
-    let root = board.root.join(".kanban");
-    let mut by_parent: std::collections::HashMap<String, Vec<CardFile>> =
-        std::collections::HashMap::new();
-    if root.exists() {
-        for e in walkdir::WalkDir::new(&root)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            if e.file_type().is_file() {
-                let p = e.path();
-                if !p
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.eq_ignore_ascii_case("md"))
-                    .unwrap_or(false)
-                {
-                    continue;
-                }
-                if let Ok(text) = fs_err::read_to_string(p) {
-                    if let Ok(card) = CardFile::from_markdown(&text) {
-                        if let Some(parent) = card.front_matter.parent.as_deref() {
-                            by_parent
-                                .entry(parent.to_uppercase())
-                                .or_default()
-                                .push(card);
-                        }
-                    }
-                }
-            }
-        }
-    }
-    fn dfs(
-        id: &str,
-        by_parent: &std::collections::HashMap<String, Vec<CardFile>>,
-    ) -> (u32, u32, u32, u32) {
-        let mut done = 0;
-        let mut total = 0;
-        let mut done_size = 0;
-        let mut total_size = 0;
-        if let Some(ch) = by_parent.get(&id.to_uppercase()) {
-            for c in ch {
-                total += 1;
-                if let Some(sz) = c.front_matter.size {
-                    total_size += sz;
-                }
-                if c.front_matter.completed_at.is_some() {
-                    done += 1;
-                    if let Some(sz) = c.front_matter.size {
-                        done_size += sz;
-                    }
-                }
-                let (cd, ct, cds, cts) = dfs(&c.front_matter.id, by_parent);
-                done += cd;
-                total += ct;
-                done_size += cds;
-                total_size += cts;
-            }
-        }
-        (done, total, done_size, total_size)
-    }
-    let (done, total, done_size, total_size) = dfs(&parent_id.to_uppercase(), &by_parent);
+    let (done, total, done_size, total_size) = board.rollup_count_size(parent_id)?;
     let pct = if total > 0 {
         (done as f64) / (total as f64) * 100.0
     } else {
@@ -270,3 +755,124 @@ pub fn render_parent_progress(board: &Board, parent_id: &str) -> Result<String>
         "progress: {done}/{total} ({pct:.1}%) size: {done_size}/{total_size} ({pct_s:.1}%)"
     ))
 }
+
+/// Obsidian vault layout of the board as `(relative path, content)` pairs: `Board.md`, one
+/// `columns/<column>.md` index note per column and one `cards/<ID>.md` note per card. Relations
+/// become wiki links (`[[01ABC...]]`, resolvable because notes are named by id) and the front
+/// matter is flat (scalars and lists only) so Dataview can query it. `.kanban/` is only read.
+pub fn obsidian_vault(board: &Board) -> Result<Vec<(String, String)>> {
+    use kanban_model::{CardFile, SortFields};
+    use std::collections::BTreeMap;
+    let cfg = kanban_model::ColumnsToml::load(&board.root);
+    let done_like = board.done_like();
+    let mut cards: Vec<(String, CardFile)> = vec![];
+    for (path, column) in board.card_files() {
+        if let Ok(card) = fs_err::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|t| CardFile::from_markdown(&t))
+        {
+            cards.push((column, card));
+        }
+    }
+    let sort = cfg.default_sort();
+    cards.sort_by(|a, b| {
+        kanban_model::compare_by_sort(&sort, &SortFields::from(&a.1.front_matter), &SortFields::from(&b.1.front_matter))
+    });
+    let mut columns: Vec<String> = if cfg.columns.is_empty() {
+        vec!["backlog".into(), "doing".into(), "review".into()]
+    } else {
+        cfg.columns.clone()
+    };
+    for c in cards.iter().map(|(c, _)| c).chain(done_like.columns()) {
+        if !columns.iter().any(|x| x.eq_ignore_ascii_case(c)) {
+            columns.push(c.clone());
+        }
+    }
+    let mut children: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (_, c) in &cards {
+        if let Some(p) = c.front_matter.parent.as_deref() {
+            children.entry(p.to_uppercase()).or_default().push(c.front_matter.id.to_uppercase());
+        }
+    }
+    let q = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    let wiki = |id: &str| format!("[[{}]]", id.to_uppercase());
+    let wiki_list = |ids: &[String]| format!("[{}]", ids.iter().map(|i| q(&wiki(i))).collect::<Vec<_>>().join(", "));
+    let str_list = |v: &[String]| format!("[{}]", v.iter().map(|s| q(s)).collect::<Vec<_>>().join(", "));
+    // wiki link alias text cannot hold `|` or brackets
+    let alias = |t: &str| t.replace(['|', '[', ']'], " ");
+    let mut out: Vec<(String, String)> = vec![];
+    for (column, card) in &cards {
+        let fm = &card.front_matter;
+        let id = fm.id.to_uppercase();
+        // YAMLとして読める行だけを書きます（値はJSON表記＝YAMLのフロー形式）
+        let mut yaml = vec![
+            format!("id: {id}"),
+            format!("title: {}", q(&fm.title)),
+            format!("aliases: [{}]", q(&fm.title)),
+            format!("column: {}", q(column)),
+            format!("done: {}", done_like.contains(column)),
+        ];
+        let opt = |k: &str, v: &Option<String>| v.as_deref().map(|v| format!("{k}: {}", q(v)));
+        yaml.extend(opt("description", &fm.description));
+        yaml.extend(opt("lane", &fm.lane));
+        yaml.extend(opt("priority", &fm.priority));
+        yaml.extend(fm.size.map(|n| format!("size: {n}")));
+        if let Some(v) = fm.assignees.as_deref().filter(|v| !v.is_empty()) {
+            yaml.push(format!("assignees: {}", str_list(v)));
+        }
+        if let Some(v) = fm.labels.as_deref().filter(|v| !v.is_empty()) {
+            yaml.push(format!("labels: {}", str_list(v)));
+            let tags: Vec<String> = v.iter().map(|l| l.trim().replace(char::is_whitespace, "-")).collect();
+            yaml.push(format!("tags: {}", str_list(&tags)));
+        }
+        yaml.extend(fm.parent.as_deref().map(|p| format!("parent: {}", q(&wiki(p)))));
+        for (k, v) in [("depends_on", &fm.depends_on), ("relates", &fm.relates)] {
+            if let Some(v) = v.as_deref().filter(|v| !v.is_empty()) {
+                yaml.push(format!("{k}: {}", wiki_list(v)));
+            }
+        }
+        if let Some(v) = children.get(&id) {
+            yaml.push(format!("children: {}", wiki_list(v)));
+        }
+        yaml.extend(opt("created", &fm.created_at));
+        yaml.extend(opt("completed", &fm.completed_at));
+        yaml.extend(opt("due", &fm.due_date));
+        if fm.blocked == Some(true) {
+            yaml.push("blocked: true".into());
+        }
+        let mut note = format!("---\n{}\n---\n# {}\n\n", yaml.join("\n"), fm.title);
+        let body = card.body.trim();
+        if !body.is_empty() {
+            note.push_str(body);
+            note.push_str("\n\n");
+        }
+        note.push_str("## Relations\n\n");
+        note.push_str(&format!("- Column: [[columns/{column}|{column}]]\n"));
+        if let Some(p) = fm.parent.as_deref() {
+            note.push_str(&format!("- Parent: {}\n", wiki(p)));
+        }
+        let joined = |v: &[String]| v.iter().map(|i| wiki(i)).collect::<Vec<_>>().join(", ");
+        if let Some(v) = children.get(&id) {
+            note.push_str(&format!("- Children: {}\n", joined(v)));
+        }
+        if let Some(v) = fm.depends_on.as_deref().filter(|v| !v.is_empty()) {
+            note.push_str(&format!("- Depends on: {}\n", joined(v)));
+        }
+        if let Some(v) = fm.relates.as_deref().filter(|v| !v.is_empty()) {
+            note.push_str(&format!("- Relates: {}\n", joined(v)));
+        }
+        out.push((format!("cards/{id}.md"), note));
+    }
+    let mut index = String::from("# Board\n\n");
+    for column in &columns {
+        let in_col: Vec<&CardFile> = cards.iter().filter(|(c, _)| c == column).map(|(_, c)| c).collect();
+        let mut note = format!("---\ncolumn: {}\ndone: {}\ncount: {}\n---\n# {column}\n\n", q(column), done_like.contains(column), in_col.len());
+        for c in &in_col {
+            note.push_str(&format!("- [[{}|{}]]\n", c.front_matter.id.to_uppercase(), alias(&c.front_matter.title)));
+        }
+        out.push((format!("columns/{column}.md"), note));
+        index.push_str(&format!("- [[columns/{column}|{column}]]: {}\n", in_col.len()));
+    }
+    out.push(("Board.md".into(), index));
+    Ok(out)
+}
@@ -1,4 +1,4 @@
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
@@ -8,11 +8,101 @@ use kanban_model::{filename_for, CardFile};
 use serde_json::json;
 use std::io::Write;
 
+/// Soft-deleted cards live here (under `.kanban/`), outside every column scan.
+pub const TRASH_DIR: &str = ".trash";
+
+/// Archived subtrees (`kanban_archive_subtree`) are written here (under `.kanban/`); never a column.
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// Card attachments (`kanban_attach`) live in `<ATTACHMENTS_DIR>/<ID>/` (under `.kanban/`); never a column.
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// Walker filter for card scans under `.kanban`: skips hidden directories such as `.trash/`,
+/// and `.kanban/archive/` and `.kanban/attachments/`, whose markdown files are not cards.
+/// Looks at every component below the walk root, since entries above `min_depth` never reach it.
+pub fn is_card_entry(e: &walkdir::DirEntry) -> bool {
+    let comps: Vec<_> = e.path().components().map(|c| c.as_os_str().to_string_lossy()).collect();
+    let reserved = comps
+        .windows(2)
+        .any(|w| w[0] == ".kanban" && (w[1] == ARCHIVE_DIR || w[1] == ATTACHMENTS_DIR));
+    !reserved
+        && comps[comps.len().saturating_sub(e.depth())..]
+            .iter()
+            .all(|c| !c.starts_with('.'))
+}
+
+/// Crash-safe card write (optionally moving it from `from` to `to`).
+/// The new content goes to a temp file in the destination directory, the old file is renamed
+/// onto the destination, then the temp file replaces it. Each step is a single rename, so a crash
+/// leaves exactly one card file (old or new content) and never a duplicate.
+/// Stamps the card's `updated_at` first, so the caller's copy (and its index row) carries it too.
+pub fn write_card_atomic(card: &mut CardFile, from: &Path, to: &Path) -> Result<()> {
+    let dir = to
+        .parent()
+        .ok_or_else(|| anyhow!("invalid card path: {}", to.display()))?;
+    fs_err::create_dir_all(dir)?;
+    if from != to && to.exists() {
+        bail!("conflict: {} already exists", to.display());
+    }
+    let mut tmp = tempfile::Builder::new()
+        .prefix(".card-")
+        .suffix(".tmp")
+        .tempfile_in(dir)?;
+    card.touch();
+    tmp.write_all(card.to_markdown()?.as_bytes())?;
+    tmp.as_file().sync_all()?;
+    if from != to && from.exists() {
+        fs_err::rename(from, to)?;
+    }
+    tmp.persist(to)?;
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct Board {
     pub root: PathBuf,
 }
 
+/// Which columns count as done. Shared by list (includeDone), render, stats and the
+/// readiness checks (open dependencies, child progress) so they never disagree: `done` always
+/// is, plus every `[terminal.<column>]` in columns.toml (e.g. `cancelled`). Done-like columns
+/// may hold partition subdirectories (`done/2025/09/`); other columns hold cards directly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoneLike {
+    columns: Vec<String>,
+}
+
+impl DoneLike {
+    pub fn new(cfg: &kanban_model::ColumnsToml) -> Self {
+        let mut columns = vec!["done".to_string()];
+        for k in cfg.terminal.keys() {
+            if !columns.iter().any(|c| c.eq_ignore_ascii_case(k)) {
+                columns.push(k.clone());
+            }
+        }
+        Self { columns }
+    }
+
+    /// Whether cards in `column` are done (case-insensitive).
+    pub fn contains(&self, column: &str) -> bool {
+        self.columns.iter().any(|c| c.eq_ignore_ascii_case(column))
+    }
+
+    /// `done` first, then the `[terminal.*]` columns by name.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// How deep to walk a column directory for its cards.
+    pub fn scan_depth(&self, column: &str) -> usize {
+        if self.contains(column) {
+            usize::MAX
+        } else {
+            1
+        }
+    }
+}
+
 impl Board {
     pub fn new(root: impl AsRef<Path>) -> Self {
         Self {
@@ -20,6 +110,11 @@ impl Board {
         }
     }
 
+    /// Done-like classification from this board's columns.toml.
+    pub fn done_like(&self) -> DoneLike {
+        DoneLike::new(&kanban_model::ColumnsToml::load(&self.root))
+    }
+
     pub fn append_note(&self, id: &str, entry: &NoteEntry) -> Result<()> {
         let base = self.root.join(".kanban").join("notes");
         fs_err::create_dir_all(&base)?;
@@ -28,21 +123,85 @@ impl Board {
             .create(true)
             .append(true)
             .open(&path)?;
-        let line = serde_json::to_string(entry)?;
+        let mut entry = entry.clone();
+        if entry.summary.is_none() {
+            entry.summary = Some(NoteEntry::summary_of(&entry.text)).filter(|s| !s.is_empty());
+        }
+        let line = serde_json::to_string(&entry)?;
         writeln!(f, "{line}")?;
         Ok(())
     }
 
+    /// Append one audit event to `.kanban/history/<ID>.ndjson`.
+    pub fn append_history(&self, id: &str, event: &kanban_model::HistoryEvent) -> Result<()> {
+        let base = self.root.join(".kanban").join("history");
+        fs_err::create_dir_all(&base)?;
+        let path = base.join(format!("{}.ndjson", id.to_uppercase()));
+        let mut f = fs_err::OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(f, "{}", serde_json::to_string(event)?)?;
+        Ok(())
+    }
+
+    /// Audit events of a card, oldest first; unreadable lines are skipped.
+    pub fn read_history(&self, id: &str) -> Result<Vec<kanban_model::HistoryEvent>> {
+        let path = self.root.join(".kanban").join("history").join(format!("{}.ndjson", id.to_uppercase()));
+        let Ok(text) = fs_err::read_to_string(&path) else {
+            return Ok(vec![]);
+        };
+        Ok(text.lines().filter_map(|l| serde_json::from_str(l.trim()).ok()).collect())
+    }
+
+    /// Undoable operations, oldest first; unreadable lines are skipped.
+    pub fn read_operations(&self) -> Result<Vec<kanban_model::Operation>> {
+        let Ok(text) = fs_err::read_to_string(self.root.join(".kanban").join("operations.ndjson")) else {
+            return Ok(vec![]);
+        };
+        Ok(text.lines().filter_map(|l| serde_json::from_str(l.trim()).ok()).collect())
+    }
+
+    /// Append an operation, keeping only the newest `OPERATIONS_KEPT`.
+    pub fn append_operation(&self, op: &kanban_model::Operation) -> Result<()> {
+        let mut ops = self.read_operations()?;
+        ops.push(op.clone());
+        let skip = ops.len().saturating_sub(OPERATIONS_KEPT);
+        self.write_operations(&ops[skip..])
+    }
+
+    /// Replace `.kanban/operations.ndjson` with `ops`.
+    pub fn write_operations(&self, ops: &[kanban_model::Operation]) -> Result<()> {
+        let base = self.root.join(".kanban");
+        fs_err::create_dir_all(&base)?;
+        let mut tmp = tempfile::NamedTempFile::new_in(&base)?;
+        for op in ops {
+            writeln!(tmp, "{}", serde_json::to_string(op)?)?;
+        }
+        tmp.persist(base.join("operations.ndjson"))?;
+        Ok(())
+    }
+
+    /// First note of the card carrying `key` (exact match), if any.
+    pub fn find_note_by_key(&self, id: &str, key: &str) -> Result<Option<NoteEntry>> {
+        Ok(self
+            .list_notes(id, None, true)?
+            .into_iter()
+            .rev()
+            .find(|n| n.key.as_deref() == Some(key)))
+    }
+
     pub fn list_notes(&self, id: &str, limit: Option<usize>, all: bool) -> Result<Vec<NoteEntry>> {
-        self.list_notes_advanced(id, limit, all, None)
+        self.list_notes_advanced(id, limit, all, None, None)
     }
 
+    /// Notes of a card, newest first, limited to `since..=until` when given. Timestamps are
+    /// compared as instants, so notes written with non-UTC offsets are ordered correctly; notes
+    /// whose `ts` does not parse are left out of bounded queries.
     pub fn list_notes_advanced(
         &self,
         id: &str,
         limit: Option<usize>,
         all: bool,
-        since: Option<&str>,
+        since: Option<OffsetDateTime>,
+        until: Option<OffsetDateTime>,
     ) -> Result<Vec<NoteEntry>> {
         let path = self
             .root
@@ -60,9 +219,11 @@ impl Board {
                 continue;
             }
             if let Ok(v) = serde_json::from_str::<NoteEntry>(line) {
-                if let Some(since_s) = since {
-                    // Best-effort string compare (our timestamps are RFC3339 UTC by default)
-                    if v.ts.as_str() < since_s {
+                if since.is_some() || until.is_some() {
+                    let Ok(ts) = OffsetDateTime::parse(&v.ts, &Rfc3339) else {
+                        continue;
+                    };
+                    if since.is_some_and(|s| ts < s) || until.is_some_and(|u| ts > u) {
                         continue;
                     }
                 }
@@ -78,6 +239,7 @@ impl Board {
         Ok(items.into_iter().take(n).collect())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new_card(
         &self,
         title: &str,
@@ -96,18 +258,55 @@ impl Board {
         card.front_matter.labels = labels;
         card.front_matter.assignees = assignees;
         if let Some(b) = body { card.body = b; }
+        self.create_card(card, column)
+    }
 
+    /// Write a fully built card (e.g. from `CardFile::new_with_title`) into `column` and index it.
+    pub fn create_card(&self, mut card: CardFile, column: &str) -> Result<String> {
+        card.front_matter.normalize_ids();
         let id = card.front_matter.id.clone();
-        let filename = filename_for(&id, title);
+        let filename = filename_for(&id, &card.front_matter.title);
         let dir = self.root.join(".kanban").join(column);
         fs_err::create_dir_all(&dir)?;
         let path = dir.join(filename);
-        fs_err::write(&path, card.to_markdown()?)?;
+        write_card_atomic(&mut card, &path, &path)?;
         // index upsert
         self.upsert_card_index(&card, column, &path)?;
         Ok(id)
     }
 
+    /// Create several cards with a single cards.ndjson rewrite. Results follow the input order;
+    /// a card whose file cannot be written gets its own error and is left out of the index.
+    pub fn create_cards(&self, cards: Vec<(CardFile, String)>) -> Result<Vec<Result<(String, PathBuf)>>> {
+        let mut out = Vec::with_capacity(cards.len());
+        let mut written: Vec<(CardFile, String, PathBuf)> = vec![];
+        for (mut card, column) in cards {
+            card.front_matter.normalize_ids();
+            let id = card.front_matter.id.clone();
+            let path = self
+                .root
+                .join(".kanban")
+                .join(&column)
+                .join(filename_for(&id, &card.front_matter.title));
+            let res = fs_err::create_dir_all(path.parent().unwrap_or(&self.root))
+                .map_err(anyhow::Error::from)
+                .and_then(|_| write_card_atomic(&mut card, &path, &path));
+            match res {
+                Ok(()) => {
+                    out.push(Ok((id, path.clone())));
+                    written.push((card, column, path));
+                }
+                Err(e) => out.push(Err(e)),
+            }
+        }
+        let entries: Vec<(&CardFile, &str, &Path)> = written
+            .iter()
+            .map(|(c, col, p)| (c, col.as_str(), p.as_path()))
+            .collect();
+        self.upsert_card_indexes(&entries)?;
+        Ok(out)
+    }
+
     pub fn read_card_text(&self, id: &str) -> Result<String> {
         let (path, _fm) = self.find_path_by_id(id)?;
         Ok(fs_err::read_to_string(path)?)
@@ -119,46 +318,232 @@ impl Board {
     }
 
     pub fn move_card(&self, id: &str, to_column: &str) -> Result<()> {
-        let (path, fm) = self.find_path_by_id(id)?;
-        let filename = filename_for(&fm.id, &fm.title);
-        let dest_dir = self.root.join(".kanban").join(to_column);
-        fs_err::create_dir_all(&dest_dir)?;
-        let dest = dest_dir.join(filename);
-        fs_err::rename(path, dest.clone())?;
+        let (path, _fm) = self.find_path_by_id(id)?;
+        let mut card = CardFile::from_markdown(&fs_err::read_to_string(&path)?)?;
+        let filename = filename_for(&card.front_matter.id, &card.front_matter.title);
+        let dest = self.root.join(".kanban").join(to_column).join(filename);
+        if dest != path {
+            write_card_atomic(&mut card, &path, &dest)?;
+        }
         // index upsert with new column
-        let card = self.read_card(id)?;
         self.upsert_card_index(&card, to_column, &dest)?;
         Ok(())
     }
 
     pub fn done_card(&self, id: &str) -> Result<()> {
+        self.finalize_card(id, "done").map(|_| ())
+    }
+
+    /// The card's path when it already sits in terminal `column` and carries `completed_at`
+    /// (or the column does not stamp it). Finalizing such a card again is a no-op.
+    pub fn finalized_path(&self, id: &str, column: &str) -> Option<PathBuf> {
+        let policy = kanban_model::ColumnsToml::load(&self.root).terminal_policy(column)?;
+        let (path, current) = self.locate_card(id)?;
+        if !current.eq_ignore_ascii_case(column) {
+            return None;
+        }
+        let card = CardFile::from_markdown(&fs_err::read_to_string(&path).ok()?).ok()?;
+        (card.front_matter.completed_at.is_some() || !policy.stamp_completed_at).then_some(path)
+    }
+
+    /// Move a card into a terminal column (`done` or `[terminal.<column>]` in columns.toml):
+    /// stamp `completed_at` when the policy says so, file it under the column's partition
+    /// (e.g. `cancelled/2025/09/`), and update the index. Returns the new path.
+    /// A card already finalized in that column keeps its `completed_at` and file.
+    pub fn finalize_card(&self, id: &str, column: &str) -> Result<PathBuf> {
+        let cfg = kanban_model::ColumnsToml::load(&self.root);
+        let Some(policy) = cfg.terminal_policy(column) else {
+            bail!("invalid-argument: column {column} is not terminal (add [terminal.{column}] to columns.toml)");
+        };
+        if let Some(path) = self.finalized_path(id, column) {
+            return Ok(path);
+        }
         let (path, mut card) = {
             let (p, _fm) = self.find_path_by_id(id)?;
             let text = fs_err::read_to_string(&p)?;
             (p, CardFile::from_markdown(&text)?)
         };
-        card.front_matter.completed_at = Some(
-            OffsetDateTime::now_utc()
-                .format(&Rfc3339)
-                .unwrap_or_default(),
-        );
-        fs_err::write(&path, card.to_markdown()?)?;
-        let now = OffsetDateTime::now_utc();
-        let year = now.year();
-        let month: u8 = now.month().into();
-        let dest_dir = self
+        let now = kanban_model::now();
+        if policy.stamp_completed_at {
+            card.front_matter.completed_at = Some(policy.stamp(now));
+        }
+        card.front_matter.normalize_ids();
+        let filename = filename_for(&card.front_matter.id, &card.front_matter.title);
+        let dest = self
             .root
             .join(".kanban")
-            .join("done")
-            .join(format!("{year:04}"))
-            .join(format!("{month:02}"));
-        fs_err::create_dir_all(&dest_dir)?;
-        let filename = filename_for(&card.front_matter.id, &card.front_matter.title);
-        let dest = dest_dir.join(filename);
-        fs_err::rename(path, dest.clone())?;
+            .join(column)
+            .join(policy.subdir(now))
+            .join(filename);
+        write_card_atomic(&mut card, &path, &dest)?;
         // index upsert with new column
-        let card = self.read_card(id)?;
-        self.upsert_card_index(&card, "done", &dest)?;
+        self.upsert_card_index(&card, column, &dest)?;
+        Ok(dest)
+    }
+
+    /// Where a card sits among the columns: (path, column). Trashed cards are not found.
+    pub fn locate_card(&self, id: &str) -> Option<(PathBuf, String)> {
+        self.card_files().find(|(p, _)| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.split_once("__"))
+                .is_some_and(|(fid, _)| fid.eq_ignore_ascii_case(id))
+        })
+    }
+
+    /// The card's file in `.kanban/.trash/`, if it was deleted.
+    pub fn trashed_card_path(&self, id: &str) -> Option<PathBuf> {
+        fs_err::read_dir(self.root.join(".kanban").join(TRASH_DIR))
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .find(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.split_once("__"))
+                    .is_some_and(|(fid, _)| fid.eq_ignore_ascii_case(id))
+            })
+    }
+
+    /// Soft delete: move the card into `.kanban/.trash/` with `deleted_from`/`deleted_at` in its
+    /// front matter, and drop it from cards.ndjson, done.ndjson and its outgoing relations.
+    /// Returns (column it left, trash path).
+    pub fn trash_card(&self, id: &str) -> Result<(String, PathBuf)> {
+        let (path, column) = self.locate_card(id).ok_or_else(|| anyhow!("not-found: card {id}"))?;
+        let mut card = CardFile::from_markdown(&fs_err::read_to_string(&path)?)?;
+        card.front_matter.deleted_from = Some(column.clone());
+        card.front_matter.deleted_at = Some(kanban_model::now().format(&Rfc3339).unwrap_or_default());
+        let dest = self
+            .root
+            .join(".kanban")
+            .join(TRASH_DIR)
+            .join(filename_for(&card.front_matter.id, &card.front_matter.title));
+        write_card_atomic(&mut card, &path, &dest)?;
+        self.remove_card_index(id)?;
+        self.set_card_relations(id, &[])?;
+        Ok((column, dest))
+    }
+
+    /// Bring a trashed card back into the column it was deleted from (done-like columns get
+    /// their partition from `completed_at`), restoring its index rows and relations.
+    /// Returns (column, path).
+    pub fn restore_card(&self, id: &str) -> Result<(String, PathBuf)> {
+        let path = self
+            .trashed_card_path(id)
+            .ok_or_else(|| anyhow!("not-found: card {id} is not in the trash"))?;
+        if self.locate_card(id).is_some() {
+            bail!("conflict: card {id} already exists outside the trash");
+        }
+        let mut card = CardFile::from_markdown(&fs_err::read_to_string(&path)?)?;
+        let column = card.front_matter.deleted_from.take().unwrap_or_else(|| "backlog".into());
+        card.front_matter.deleted_at = None;
+        let cfg = kanban_model::ColumnsToml::load(&self.root);
+        let subdir = match cfg.terminal_policy(&column) {
+            Some(policy) => {
+                let at = card
+                    .front_matter
+                    .completed_at
+                    .as_deref()
+                    .and_then(|s| OffsetDateTime::parse(s, &Rfc3339).ok())
+                    .unwrap_or_else(OffsetDateTime::now_utc);
+                policy.subdir(at)
+            }
+            None => PathBuf::new(),
+        };
+        let dest = self
+            .root
+            .join(".kanban")
+            .join(&column)
+            .join(subdir)
+            .join(filename_for(&card.front_matter.id, &card.front_matter.title));
+        write_card_atomic(&mut card, &path, &dest)?;
+        self.upsert_card_index(&card, &column, &dest)?;
+        self.set_card_relations(id, &relation_edges_of(&card))?;
+        Ok((column, dest))
+    }
+
+    /// `.kanban/attachments/<ID>/`
+    pub fn attachments_dir(&self, id: &str) -> PathBuf {
+        self.root.join(".kanban").join(ATTACHMENTS_DIR).join(id.to_uppercase())
+    }
+
+    /// Store `bytes` as attachment `name` of card `id`. The name must be a plain file name;
+    /// an existing attachment is replaced only with `overwrite`. Returns the written path.
+    pub fn write_attachment(&self, id: &str, name: &str, bytes: &[u8], overwrite: bool) -> Result<PathBuf> {
+        let path = self.attachment_path(id, name)?;
+        if path.exists() && !overwrite {
+            bail!("conflict: attachment {name} already exists on card {} (pass overwrite:true to replace)", id.to_uppercase());
+        }
+        let dir = self.attachments_dir(id);
+        fs_err::create_dir_all(&dir)?;
+        let mut tmp = tempfile::NamedTempFile::new_in(&dir)?;
+        tmp.write_all(bytes)?;
+        tmp.persist(&path)?;
+        Ok(path)
+    }
+
+    /// Path of attachment `name` of card `id`; rejects names that would leave the card's directory.
+    pub fn attachment_path(&self, id: &str, name: &str) -> Result<PathBuf> {
+        let plain = !name.is_empty()
+            && !name.starts_with('.')
+            && !name.contains(['/', '\\', ':'])
+            && !name.chars().any(|c| c.is_control());
+        if !plain {
+            bail!("invalid-argument: attachment name must be a plain file name: {name:?}");
+        }
+        Ok(self.attachments_dir(id).join(name))
+    }
+
+    /// Hard delete: remove the card file (live or trashed) and its notes, attachments, index rows and
+    /// outgoing relations. Returns the removed path.
+    pub fn purge_card(&self, id: &str) -> Result<PathBuf> {
+        let path = match self.locate_card(id) {
+            Some((p, _)) => p,
+            None => self.trashed_card_path(id).ok_or_else(|| anyhow!("not-found: card {id}"))?,
+        };
+        fs_err::remove_file(&path)?;
+        let notes = self.root.join(".kanban").join("notes").join(format!("{}.ndjson", id.to_uppercase()));
+        if notes.exists() {
+            fs_err::remove_file(notes)?;
+        }
+        let attachments = self.attachments_dir(id);
+        if attachments.exists() {
+            fs_err::remove_dir_all(attachments)?;
+        }
+        self.remove_card_index(id)?;
+        self.set_card_relations(id, &[])?;
+        Ok(path)
+    }
+
+    /// Drop a card's cards.ndjson row; a done card also gets a `reopened` row in done.ndjson.
+    fn remove_card_index(&self, id: &str) -> Result<()> {
+        let idx = self.root.join(".kanban").join("cards.ndjson");
+        let Ok(text) = fs_err::read_to_string(&idx) else {
+            return Ok(());
+        };
+        let done_like = self.done_like();
+        let mut was_done = false;
+        let mut out = String::new();
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let row = serde_json::from_str::<serde_json::Value>(line).ok();
+            let row_id = row.as_ref().and_then(|v| v.get("id")).and_then(|x| x.as_str()).unwrap_or("");
+            if row_id.eq_ignore_ascii_case(id) {
+                was_done |= row
+                    .as_ref()
+                    .and_then(|v| v.get("column"))
+                    .and_then(|x| x.as_str())
+                    .is_some_and(|c| done_like.contains(c));
+                continue;
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+        let mut tmp = tempfile::NamedTempFile::new_in(self.root.join(".kanban"))?;
+        tmp.write_all(out.as_bytes())?;
+        tmp.persist(&idx)?;
+        if was_done {
+            self.append_done_index(vec![DoneRecord { id: id.to_uppercase(), column: TRASH_DIR.into(), reopened: true, ..Default::default() }])?;
+        }
         Ok(())
     }
 
@@ -183,10 +568,26 @@ impl Board {
         let root = self.root.join(".kanban");
         fs_err::create_dir_all(&root)?;
         let idx = root.join("cards.ndjson");
+        // 既存索引の (id, column) -> in_column_since を引き継ぎ、無ければファイルのmtimeで代用します
+        let mut prev_since: std::collections::HashMap<(String, String), String> = Default::default();
+        if let Ok(text) = fs_err::read_to_string(&idx) {
+            for line in text.lines() {
+                if let Ok(v) = serde_json::from_str::<serde_json::Value>(line) {
+                    if let (Some(id), Some(col), Some(since)) = (
+                        v.get("id").and_then(|x| x.as_str()),
+                        v.get("column").and_then(|x| x.as_str()),
+                        v.get("in_column_since").and_then(|x| x.as_str()),
+                    ) {
+                        prev_since.insert((id.to_uppercase(), col.to_string()), since.to_string());
+                    }
+                }
+            }
+        }
         let mut out = String::new();
         if root.exists() {
             for e in walkdir::WalkDir::new(&root)
                 .into_iter()
+                .filter_entry(is_card_entry)
                 .filter_map(|e| e.ok())
             {
                 if e.file_type().is_file() {
@@ -215,15 +616,31 @@ impl Board {
                         Err(_) => continue,
                     };
                     if let Ok(card) = CardFile::from_markdown(&text) {
+                        let since = prev_since
+                            .get(&(card.front_matter.id.to_uppercase(), column.clone()))
+                            .cloned()
+                            .or_else(|| {
+                                let m = fs_err::metadata(p).ok()?.modified().ok()?;
+                                OffsetDateTime::from(m).format(&Rfc3339).ok()
+                            });
                         let v = json!({
-                            "id": card.front_matter.id,
+                            "id": card.front_matter.id.to_uppercase(),
                             "title": card.front_matter.title,
+                            "description": card.front_matter.description,
                             "column": column,
                             "lane": card.front_matter.lane,
                             "priority": card.front_matter.priority,
+                            "size": card.front_matter.size,
                             "labels": card.front_matter.labels,
                             "assignees": card.front_matter.assignees,
                             "completed_at": card.front_matter.completed_at,
+                            "updated_at": card.front_matter.updated_at,
+                            "resolution": card.front_matter.resolution,
+                            "position": card.front_matter.position,
+                            "due_date": card.front_matter.due_date,
+                            "blocked": card.front_matter.blocked.unwrap_or(false),
+                            "in_column_since": since,
+                            "path": p.strip_prefix(&self.root).unwrap_or(p).to_string_lossy(),
                         });
                         out.push_str(&serde_json::to_string(&v)?);
                         out.push('\n');
@@ -232,20 +649,97 @@ impl Board {
             }
         }
         fs_err::write(idx, out)?;
+        self.reindex_done()?;
+        Ok(())
+    }
+
+    /// Rebuild `.kanban/done.ndjson` from the card files in done-like columns (oldest
+    /// completion first). Returns the number of rows.
+    pub fn reindex_done(&self) -> Result<usize> {
+        let done_like = self.done_like();
+        let mut rows: Vec<DoneRecord> = vec![];
+        for (p, column) in self.card_files() {
+            if !done_like.contains(&column) {
+                continue;
+            }
+            let Ok(text) = fs_err::read_to_string(&p) else { continue };
+            let Ok(card) = CardFile::from_markdown(&text) else { continue };
+            let rel = p.strip_prefix(&self.root).unwrap_or(&p).to_string_lossy().to_string();
+            rows.push(DoneRecord::from_card(&card, &column, rel));
+        }
+        // completed_at may carry different offsets; order by the instant
+        let at = |r: &DoneRecord| r.completed_at.as_deref().and_then(|s| OffsetDateTime::parse(s, &Rfc3339).ok());
+        rows.sort_by(|a, b| (at(a), &a.id).cmp(&(at(b), &b.id)));
+        let base = self.root.join(".kanban");
+        fs_err::create_dir_all(&base)?;
+        let mut tmp = tempfile::NamedTempFile::new_in(&base)?;
+        for r in &rows {
+            writeln!(tmp, "{}", serde_json::to_string(r)?)?;
+        }
+        tmp.persist(base.join("done.ndjson"))?;
+        Ok(rows.len())
+    }
+
+    /// Done cards from `.kanban/done.ndjson` (last row per id, reopened cards dropped), or
+    /// `None` when the file does not exist.
+    pub fn read_done_index(&self) -> Option<Vec<DoneRecord>> {
+        let text = fs_err::read_to_string(self.root.join(".kanban").join("done.ndjson")).ok()?;
+        let mut by_id: std::collections::HashMap<String, DoneRecord> = Default::default();
+        let mut order: Vec<String> = vec![];
+        for r in text.lines().filter_map(|l| serde_json::from_str::<DoneRecord>(l).ok()) {
+            let id = r.id.to_uppercase();
+            if by_id.insert(id.clone(), r).is_none() {
+                order.push(id);
+            }
+        }
+        Some(
+            order
+                .into_iter()
+                .filter_map(|id| by_id.remove(&id))
+                .filter(|r| !r.reopened)
+                .collect(),
+        )
+    }
+
+    /// Keep done.ndjson in step with an index upsert: cards filed into a done-like column are
+    /// appended, cards that left one get a `reopened` row. A missing file is built in full.
+    fn append_done_index(&self, rows: Vec<DoneRecord>) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let path = self.root.join(".kanban").join("done.ndjson");
+        if !path.exists() {
+            return self.reindex_done().map(|_| ());
+        }
+        let mut f = fs_err::OpenOptions::new().append(true).open(&path)?;
+        for r in rows {
+            writeln!(f, "{}", serde_json::to_string(&r)?)?;
+        }
         Ok(())
     }
 
     pub fn reindex_relations(&self) -> Result<()> {
-        use serde_json::json;
         let root = self.root.join(".kanban");
         fs_err::create_dir_all(&root)?;
         let idx = root.join("relations.ndjson");
         let mut out = String::new();
-        let mut ids = std::collections::HashSet::new();
+        for e in self.relations_from_cards()? {
+            out.push_str(&serde_json::to_string(&e)?);
+            out.push('\n');
+        }
+        fs_err::write(idx, out)?;
+        let _ = fs_err::remove_file(root.join(RELATIONS_RECORDS_FILE));
+        Ok(())
+    }
+
+    /// Edges derived from card front matter (the source of truth for relations.ndjson).
+    pub fn relations_from_cards(&self) -> Result<Vec<RelationEdge>> {
+        let root = self.root.join(".kanban");
         let mut cards: Vec<CardFile> = vec![];
         if root.exists() {
             for e in walkdir::WalkDir::new(&root)
                 .into_iter()
+                .filter_entry(is_card_entry)
                 .filter_map(|e| e.ok())
             {
                 if e.file_type().is_file() {
@@ -260,42 +754,265 @@ impl Board {
                     }
                     if let Ok(text) = fs_err::read_to_string(p) {
                         if let Ok(card) = CardFile::from_markdown(&text) {
-                            ids.insert(card.front_matter.id.to_uppercase());
                             cards.push(card);
                         }
                     }
                 }
             }
         }
-        for c in cards {
-            let idu = c.front_matter.id.to_uppercase();
-            if let Some(p) = c.front_matter.parent.as_deref() {
-                let v = json!({"type":"parent","from": idu, "to": p.to_uppercase()});
-                out.push_str(&serde_json::to_string(&v)?);
-                out.push('\n');
+        Ok(cards.iter().flat_map(relation_edges_of).collect())
+    }
+
+    /// Edges currently stored in `.kanban/relations.ndjson` (empty when the file is absent).
+    /// Edge rows form the compacted base; a later per-card record replaces every earlier edge
+    /// whose `from` is that card. Unparsable lines are skipped.
+    pub fn read_relations_index(&self) -> Result<Vec<RelationEdge>> {
+        Ok(self
+            .read_relations_by_card()?
+            .into_values()
+            .flatten()
+            .collect())
+    }
+
+    /// Parent of each child card (uppercase ids), from relations.ndjson; boards without the
+    /// index fall back to card front matter.
+    pub fn parents_by_child(&self) -> Result<std::collections::HashMap<String, String>> {
+        let edges = if self.root.join(".kanban").join("relations.ndjson").exists() {
+            self.read_relations_index()?
+        } else {
+            self.relations_from_cards()?
+        };
+        Ok(edges
+            .into_iter()
+            .filter(|e| e.kind == "parent")
+            .map(|e| (e.from.to_uppercase(), e.to.to_uppercase()))
+            .collect())
+    }
+
+    /// Folded index keyed by `from`.
+    fn read_relations_by_card(&self) -> Result<std::collections::BTreeMap<String, Vec<RelationEdge>>> {
+        let mut by_card: std::collections::BTreeMap<String, Vec<RelationEdge>> = Default::default();
+        let idx = self.root.join(".kanban").join("relations.ndjson");
+        if !idx.exists() {
+            return Ok(by_card);
+        }
+        for line in fs_err::read_to_string(&idx)?.lines().filter(|l| !l.trim().is_empty()) {
+            if let Ok(e) = serde_json::from_str::<RelationEdge>(line) {
+                let e = RelationEdge::new(&e.kind, &e.from, &e.to);
+                by_card.entry(e.from.clone()).or_default().push(e);
+            } else if let Ok(r) = serde_json::from_str::<CardRelations>(line) {
+                let id = r.card.to_uppercase();
+                by_card.insert(id.clone(), r.edges(&id));
             }
-            if let Some(ds) = c.front_matter.depends_on.as_ref() {
-                for d in ds {
-                    let v = json!({"type":"depends","from": idu, "to": d.to_uppercase()});
-                    out.push_str(&serde_json::to_string(&v)?);
-                    out.push('\n');
+        }
+        by_card.retain(|_, edges| !edges.is_empty());
+        Ok(by_card)
+    }
+
+    /// Record the complete outgoing edge set of one card by appending a single line, so a
+    /// relation change costs one small write instead of rewriting the index. Edges whose `from`
+    /// is another card are ignored. Compacts the file once records pile up.
+    pub fn set_card_relations(&self, card: &str, edges: &[RelationEdge]) -> Result<()> {
+        let id = card.to_uppercase();
+        let mut rec = CardRelations {
+            card: id.clone(),
+            ..Default::default()
+        };
+        for e in edges.iter().filter(|e| e.from.eq_ignore_ascii_case(&id)) {
+            let to = e.to.to_uppercase();
+            let list = match e.kind.to_lowercase().as_str() {
+                "parent" => {
+                    rec.parent = Some(to);
+                    continue;
                 }
+                "depends" => &mut rec.depends,
+                "relates" => &mut rec.relates,
+                other => bail!("invalid-argument: unknown relation type {other}"),
+            };
+            if !list.contains(&to) {
+                list.push(to);
             }
-            if let Some(rs) = c.front_matter.relates.as_ref() {
-                for r in rs {
-                    let v = json!({"type":"relates","from": idu, "to": r.to_uppercase()});
-                    out.push_str(&serde_json::to_string(&v)?);
-                    out.push('\n');
+        }
+        let base = self.root.join(".kanban");
+        fs_err::create_dir_all(&base)?;
+        let mut f = fs_err::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(base.join("relations.ndjson"))?;
+        writeln!(f, "{}", serde_json::to_string(&rec)?)?;
+        drop(f);
+        // The record count lives in a tiny sidecar so deciding to compact does not read the index
+        let counter = base.join(RELATIONS_RECORDS_FILE);
+        let records = fs_err::read_to_string(&counter)
+            .ok()
+            .and_then(|t| t.trim().parse::<usize>().ok())
+            .unwrap_or(0)
+            + 1;
+        if records >= RELATIONS_COMPACT_AFTER {
+            self.compact_relations_index()?;
+        } else {
+            fs_err::write(&counter, records.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite relations.ndjson as plain edge rows (per-card records folded in, removed edges
+    /// dropped). Returns the number of edges kept.
+    pub fn compact_relations_index(&self) -> Result<usize> {
+        let edges = self.read_relations_index()?;
+        let base = self.root.join(".kanban");
+        fs_err::create_dir_all(&base)?;
+        let mut out = String::new();
+        for e in &edges {
+            out.push_str(&serde_json::to_string(e)?);
+            out.push('\n');
+        }
+        let tmp = base.join("relations.ndjson.tmp");
+        fs_err::write(&tmp, out)?;
+        fs_err::rename(&tmp, base.join("relations.ndjson"))?;
+        let _ = fs_err::remove_file(base.join(RELATIONS_RECORDS_FILE));
+        Ok(edges.len())
+    }
+
+    /// Compare relations.ndjson against front matter.
+    pub fn verify_relations_index(&self) -> Result<RelationsDrift> {
+        use std::collections::BTreeSet;
+        let truth: BTreeSet<RelationEdge> = self.relations_from_cards()?.into_iter().collect();
+        let index: BTreeSet<RelationEdge> = self.read_relations_index()?.into_iter().collect();
+        Ok(RelationsDrift {
+            missing: truth.difference(&index).cloned().collect(),
+            stale: index.difference(&truth).cloned().collect(),
+        })
+    }
+
+    /// Compare cards.ndjson against the card files: ids on disk without a row (`missing`), rows
+    /// without a file (`stale`) and rows whose column or path no longer matches (`moved`).
+    pub fn verify_cards_index(&self) -> Result<CardsIndexDrift> {
+        use std::collections::BTreeMap;
+        let mut disk: BTreeMap<String, (String, String)> = BTreeMap::new();
+        for (p, column) in self.card_files() {
+            let Some(id) = p
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.split_once("__"))
+                .map(|(id, _)| id.to_uppercase())
+            else {
+                continue;
+            };
+            let rel = p.strip_prefix(&self.root).unwrap_or(&p).to_string_lossy().to_string();
+            disk.insert(id, (column, rel));
+        }
+        let mut index: BTreeMap<String, (String, String)> = BTreeMap::new();
+        let text = fs_err::read_to_string(self.root.join(".kanban").join("cards.ndjson")).unwrap_or_default();
+        for v in text.lines().filter_map(|l| serde_json::from_str::<serde_json::Value>(l).ok()) {
+            let s = |k: &str| v.get(k).and_then(|x| x.as_str()).unwrap_or_default().to_string();
+            index.insert(s("id").to_uppercase(), (s("column"), s("path")));
+        }
+        let mut drift = CardsIndexDrift::default();
+        for (id, (column, path)) in &disk {
+            match index.get(id) {
+                None => drift.missing.push(id.clone()),
+                Some((c, p)) if !c.eq_ignore_ascii_case(column) || Path::new(p) != Path::new(path) => {
+                    drift.moved.push(id.clone())
                 }
+                Some(_) => {}
             }
         }
-        fs_err::write(idx, out)?;
-        Ok(())
+        drift.stale = index.keys().filter(|id| !disk.contains_key(*id)).cloned().collect();
+        Ok(drift)
     }
 
-    pub fn compact_dirs(&self) -> Result<()> {
-        // No-op minimal implementation
-        Ok(())
+    /// Card files in done-like columns that are not where their column's partition policy
+    /// puts them by `completed_at`: `(from, to)` absolute paths. Cards without `completed_at`
+    /// are only filed (by file mtime) when they sit directly in the column directory.
+    pub fn partition_moves(&self) -> Vec<(PathBuf, PathBuf)> {
+        let cfg = kanban_model::ColumnsToml::load(&self.root);
+        let base = self.root.join(".kanban");
+        let mut moves = vec![];
+        for (p, column) in self.card_files() {
+            let Some(policy) = cfg.terminal_policy(&column) else {
+                continue;
+            };
+            let (Some(name), Some(dir)) = (p.file_name(), p.parent()) else {
+                continue;
+            };
+            let completed = fs_err::read_to_string(&p)
+                .ok()
+                .and_then(|t| CardFile::from_markdown(&t).ok())
+                .and_then(|c| c.front_matter.completed_at)
+                .and_then(|ca| kanban_model::parse_since(&ca));
+            let at = match completed {
+                Some(at) => at,
+                None if dir == base.join(&column) => fs_err::metadata(&p)
+                    .and_then(|m| m.modified())
+                    .map(OffsetDateTime::from)
+                    .unwrap_or(OffsetDateTime::UNIX_EPOCH),
+                None => continue,
+            };
+            let dest = base.join(&column).join(policy.subdir(at)).join(name);
+            if dest != p {
+                moves.push((p, dest));
+            }
+        }
+        moves.sort();
+        moves
+    }
+
+    /// Remove empty directories under `.kanban/` (deepest first). Returns them relative to `.kanban/`.
+    pub fn compact_dirs(&self) -> Result<Vec<String>> {
+        let base = self.root.join(".kanban");
+        let mut dirs: Vec<PathBuf> = walkdir::WalkDir::new(&base)
+            .min_depth(1)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+            .map(|e| e.into_path())
+            .collect();
+        dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+        let mut removed = vec![];
+        for d in dirs {
+            if fs_err::read_dir(&d).is_ok_and(|mut it| it.next().is_none()) {
+                fs_err::remove_dir(&d)?;
+                removed.push(d.strip_prefix(&base).unwrap_or(&d).to_string_lossy().to_string());
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Re-file done-like cards into their partitions, optionally drop empty directories, compact
+    /// relations.ndjson and rebuild the card/done indexes. With `dry_run`, only `moves` is filled.
+    /// A card whose destination already exists is left in place and reported in `skipped`.
+    pub fn compact(&self, dry_run: bool, remove_empty_dirs: bool) -> Result<CompactReport> {
+        let rel = |p: &Path| p.strip_prefix(&self.root).unwrap_or(p).to_string_lossy().to_string();
+        let mut report = CompactReport::default();
+        for (from, to) in self.partition_moves() {
+            if to.exists() {
+                report.skipped.push(rel(&from));
+                continue;
+            }
+            if !dry_run {
+                if let Some(parent) = to.parent() {
+                    fs_err::create_dir_all(parent)?;
+                }
+                fs_err::rename(&from, &to)?;
+            }
+            report.moves.push((rel(&from), rel(&to)));
+        }
+        if dry_run || !self.root.join(".kanban").exists() {
+            return Ok(report);
+        }
+        if remove_empty_dirs {
+            report.removed_dirs = self.compact_dirs()?;
+        }
+        report.relations = self.compact_relations_index()?;
+        if report.moves.is_empty() {
+            report.done = self.reindex_done()?;
+        } else {
+            // 移動したパスを索引へ反映します（done.ndjson も作り直されます）
+            self.reindex_cards()?;
+            report.done = self.read_done_index().map_or(0, |rows| rows.len());
+        }
+        Ok(report)
     }
 
     pub fn set_parent(&self, _child: &str, _parent: Option<&str>) -> Result<()> {
@@ -326,38 +1043,590 @@ impl Board {
         bail!("unimplemented: split_new_parent_with_children")
     }
 
-    pub fn rollup_count_size(&self, _root_id: &str) -> Result<(u32, u32, u32, u32)> {
-        bail!("unimplemented: rollup_count_size")
+    /// Transitive rollup of the cards under `root_id` (via `parent`, the root itself excluded):
+    /// `(done, total, done_size, total_size)`. Cards in done-like columns count as done; a
+    /// `parent` cycle is walked once.
+    pub fn rollup_count_size(&self, root_id: &str) -> Result<(u32, u32, u32, u32)> {
+        let r = self.rollup(root_id)?;
+        Ok((r.done, r.total, r.done_size, r.total_size))
     }
 
-    fn find_path_by_id(&self, id: &str) -> Result<(PathBuf, kanban_model::CardFrontMatter)> {
+    /// `rollup_count_size` plus the checklist items of the root and every descendant.
+    pub fn rollup(&self, root_id: &str) -> Result<Rollup> {
+        use std::collections::{HashMap, HashSet};
+        let done_like = self.done_like();
+        // parent -> [(child id, size, in a done-like column)]
+        let mut by_parent: HashMap<String, Vec<(String, u32, bool)>> = HashMap::new();
+        // id -> (checked, items)
+        let mut checklists: HashMap<String, (u32, u32)> = HashMap::new();
+        for (path, column) in self.card_files() {
+            let Ok(card) = fs_err::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|t| CardFile::from_markdown(&t))
+            else {
+                continue;
+            };
+            let fm = card.front_matter;
+            if let Some((checked, items)) = fm.checklist_progress() {
+                checklists.insert(fm.id.to_uppercase(), (checked as u32, items as u32));
+            }
+            if let Some(parent) = fm.parent.as_deref() {
+                by_parent.entry(parent.to_uppercase()).or_default().push((
+                    fm.id.to_uppercase(),
+                    fm.size.unwrap_or(0),
+                    done_like.contains(&column),
+                ));
+            }
+        }
+        let mut r = Rollup::default();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut stack = vec![root_id.to_uppercase()];
+        seen.insert(root_id.to_uppercase());
+        while let Some(id) = stack.pop() {
+            if let Some((checked, items)) = checklists.get(&id) {
+                r.checklist_done += checked;
+                r.checklist_total += items;
+            }
+            for (child, size, done) in by_parent.get(&id).into_iter().flatten() {
+                if !seen.insert(child.clone()) {
+                    continue;
+                }
+                r.total += 1;
+                r.total_size += size;
+                if *done {
+                    r.done += 1;
+                    r.done_size += size;
+                }
+                stack.push(child.clone());
+            }
+        }
+        Ok(r)
+    }
+
+    /// Migration: uppercase ids in card front matter, card filenames and notes filenames,
+    /// then rebuild both indexes. With `dry_run`, only reports what would change.
+    pub fn normalize_id_case(&self, dry_run: bool) -> Result<IdCaseReport> {
         let root = self.root.join(".kanban");
+        let mut report = IdCaseReport::default();
         if !root.exists() {
-            bail!(".kanban not found: {}", root.display());
+            return Ok(report);
         }
-        for entry in walkdir::WalkDir::new(&root)
+        let files: Vec<PathBuf> = walkdir::WalkDir::new(&root)
             .into_iter()
+            .filter_entry(is_card_entry)
             .filter_map(|e| e.ok())
-        {
-            if entry.file_type().is_file() {
-                let name = entry.file_name().to_string_lossy().to_string();
-                if let Some((fid, _)) = name.split_once("__") {
-                    if fid.eq_ignore_ascii_case(id) {
-                        let text = fs_err::read_to_string(entry.path())?;
-                        let cf = CardFile::from_markdown(&text)?;
-                        return Ok((entry.path().to_path_buf(), cf.front_matter));
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.path().to_path_buf())
+            .collect();
+        let notes_dir = root.join("notes");
+        for p in files {
+            let name = p.file_name().and_then(|s| s.to_str()).unwrap_or("").to_string();
+            if p.parent() == Some(notes_dir.as_path()) {
+                let Some(stem) = name.strip_suffix(".ndjson") else {
+                    continue;
+                };
+                if stem == stem.to_uppercase() {
+                    continue;
+                }
+                report.notes_renamed.push(name.clone());
+                if !dry_run {
+                    // 大文字側が既にあれば追記して統合します
+                    let dest = notes_dir.join(format!("{}.ndjson", stem.to_uppercase()));
+                    let text = fs_err::read_to_string(&p)?;
+                    let mut f = fs_err::OpenOptions::new().create(true).append(true).open(&dest)?;
+                    f.write_all(text.as_bytes())?;
+                    fs_err::remove_file(&p)?;
+                }
+                continue;
+            }
+            if !name.to_ascii_lowercase().ends_with(".md") {
+                continue;
+            }
+            let Ok(mut card) = fs_err::read_to_string(&p)
+                .map_err(anyhow::Error::from)
+                .and_then(|t| CardFile::from_markdown(&t))
+            else {
+                continue;
+            };
+            if card.front_matter.id.is_empty() {
+                continue;
+            }
+            let mut path = p.clone();
+            if let Some((fid, rest)) = name.split_once("__") {
+                if fid != fid.to_uppercase() {
+                    report.files_renamed.push(name.clone());
+                    path = p.with_file_name(format!("{}__{}", fid.to_uppercase(), rest));
+                    if !dry_run {
+                        fs_err::rename(&p, &path)?;
                     }
                 }
             }
+            if card.front_matter.normalize_ids() {
+                report.cards_rewritten.push(card.front_matter.id.clone());
+                if !dry_run {
+                    fs_err::write(&path, card.to_markdown()?)?;
+                }
+            }
         }
-        bail!("card not found: {}", id)
+        if !dry_run {
+            self.reindex_cards()?;
+            self.reindex_relations()?;
+        }
+        Ok(report)
     }
-}
 
-#[cfg(test)]
-mod tests_notes_storage {
-    use super::*;
-    use tempfile::tempdir;
+    /// Start a fresh board at `to` from this board's layout: `columns.toml`, `templates/` and the
+    /// (empty) column directories, plus copies of the cards in `card_ids` or labelled with any of
+    /// `labels`. Copies get new ids; relations, completion, blockers and resume fields are dropped,
+    /// and cards from terminal columns land in the first column. Refuses to touch an existing board.
+    pub fn clone_layout_into(&self, to: &Path, card_ids: &[String], labels: &[String]) -> Result<CloneReport> {
+        let src = self.root.join(".kanban");
+        if !src.exists() {
+            bail!("not-found: .kanban not found: {}", src.display());
+        }
+        let dst = to.join(".kanban");
+        if dst.join("columns.toml").exists() || Board::new(to).card_files().next().is_some() {
+            bail!("conflict: {} already contains a board", to.display());
+        }
+        fs_err::create_dir_all(&dst)?;
+        let mut report = CloneReport::default();
+        if src.join("columns.toml").exists() {
+            fs_err::copy(src.join("columns.toml"), dst.join("columns.toml"))?;
+            report.files.push("columns.toml".into());
+        }
+        let tpl = src.join("templates");
+        for e in walkdir::WalkDir::new(&tpl).into_iter().filter_map(|e| e.ok()) {
+            let rel = e.path().strip_prefix(&src)?;
+            if e.file_type().is_dir() {
+                fs_err::create_dir_all(dst.join(rel))?;
+            } else if e.file_type().is_file() {
+                fs_err::copy(e.path(), dst.join(rel))?;
+                report.files.push(rel.to_string_lossy().replace('\\', "/"));
+            }
+        }
+        let cfg = kanban_model::ColumnsToml::load(&self.root);
+        let first_column = cfg.columns.first().cloned().unwrap_or_else(|| "backlog".into());
+        for c in cfg.columns.iter().filter(|c| !cfg.is_terminal(c)) {
+            fs_err::create_dir_all(dst.join(c))?;
+        }
+        let target = Board::new(to);
+        let mut picked: Vec<(String, CardFile)> = vec![];
+        for (path, column) in self.card_files() {
+            let Ok(card) = fs_err::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|t| CardFile::from_markdown(&t))
+            else {
+                continue;
+            };
+            let fm = &card.front_matter;
+            let by_id = card_ids.iter().any(|i| i.eq_ignore_ascii_case(&fm.id));
+            let by_label = fm
+                .labels
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .any(|l| labels.iter().any(|x| x.eq_ignore_ascii_case(l)));
+            if by_id || by_label {
+                picked.push((column, card));
+            }
+        }
+        for want in card_ids {
+            if !picked.iter().any(|(_, c)| c.front_matter.id.eq_ignore_ascii_case(want)) {
+                bail!("not-found: card {want} in {}", self.root.display());
+            }
+        }
+        picked.sort_by(|a, b| a.1.front_matter.id.cmp(&b.1.front_matter.id));
+        for (column, card) in picked {
+            let fm = card.front_matter;
+            let mut copy = CardFile::new_with_title(&fm.title);
+            copy.front_matter.description = fm.description;
+            copy.front_matter.lane = fm.lane;
+            copy.front_matter.priority = fm.priority;
+            copy.front_matter.size = fm.size;
+            copy.front_matter.labels = fm.labels;
+            copy.front_matter.assignees = fm.assignees;
+            copy.front_matter.position = fm.position;
+            copy.front_matter.links = fm.links;
+            copy.front_matter.next_steps = fm.next_steps;
+            copy.body = card.body;
+            let column = if cfg.is_terminal(&column) { first_column.clone() } else { column };
+            let id = target.create_card(copy, &column)?;
+            report.cards.push(ClonedCard { from: fm.id.to_uppercase(), id, column });
+        }
+        Ok(report)
+    }
+
+    /// Turn loose Markdown files under `dir` into cards in `column`: fresh ids, title from the
+    /// first heading (else the file stem), file content kept verbatim as the body. Hidden files
+    /// and anything inside `.kanban/` are ignored; `recursive` descends into subdirectories.
+    /// `originals` decides what happens to the source files once their card is written.
+    pub fn import_markdown_dir(
+        &self,
+        dir: &Path,
+        column: &str,
+        recursive: bool,
+        originals: ImportOriginals,
+        dry_run: bool,
+    ) -> Result<MarkdownImport> {
+        if !dir.is_dir() {
+            bail!("not-found: directory {}", dir.display());
+        }
+        let kanban_dir = self.root.join(".kanban").canonicalize().ok();
+        let mut report = MarkdownImport::default();
+        let mut pending: Vec<(PathBuf, String, CardFile)> = vec![];
+        let walker = walkdir::WalkDir::new(dir)
+            .min_depth(1)
+            .max_depth(if recursive { usize::MAX } else { 1 })
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'));
+        for e in walker.filter_map(|e| e.ok()) {
+            let path = e.path();
+            if !e.file_type().is_file()
+                || !path.extension().and_then(|x| x.to_str()).is_some_and(|x| x.eq_ignore_ascii_case("md"))
+            {
+                continue;
+            }
+            let abs = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            if kanban_dir.as_ref().is_some_and(|k| abs.starts_with(k)) {
+                continue;
+            }
+            let rel = path.strip_prefix(dir).unwrap_or(path).to_string_lossy().replace('\\', "/");
+            let text = match fs_err::read_to_string(path) {
+                Ok(t) => t,
+                Err(e) => {
+                    report.skipped.push(SkippedFile { file: rel, reason: e.to_string() });
+                    continue;
+                }
+            };
+            if text.trim().is_empty() {
+                report.skipped.push(SkippedFile { file: rel, reason: "empty file".into() });
+                continue;
+            }
+            let title = kanban_model::first_heading(&text).unwrap_or_else(|| {
+                path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| rel.clone())
+            });
+            let mut card = CardFile::new_with_title(&title);
+            card.body = text;
+            if originals == ImportOriginals::Link {
+                card.front_matter.links = Some(vec![kanban_model::CardLink {
+                    title: Some(format!("Imported from {rel}")),
+                    url: format!("file://{}", abs.to_string_lossy().replace('\\', "/")),
+                }]);
+            }
+            pending.push((path.to_path_buf(), rel, card));
+        }
+        if dry_run {
+            for (_, rel, card) in pending {
+                report.imported.push(ImportedFile { file: rel, id: None, title: card.front_matter.title });
+            }
+            return Ok(report);
+        }
+        let cards = pending.iter().map(|(_, _, c)| (c.clone(), column.to_string())).collect();
+        for ((path, rel, card), res) in pending.into_iter().zip(self.create_cards(cards)?) {
+            match res {
+                Ok((id, _)) => {
+                    if originals == ImportOriginals::Move {
+                        fs_err::remove_file(&path)?;
+                    }
+                    report.imported.push(ImportedFile { file: rel, id: Some(id), title: card.front_matter.title });
+                }
+                Err(e) => report.skipped.push(SkippedFile { file: rel, reason: e.to_string() }),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Convert another tool's export (`format`) into cards. Open items go to `column`, items in
+    /// progress to `doing` and finished ones to `done` (filed under its partition by their
+    /// completion time); a status naming a configured column goes there. Every item lists what
+    /// could not be carried over in `unmapped`. The index is updated once.
+    pub fn import_tasks(&self, file: &Path, format: TaskFormat, column: &str, dry_run: bool) -> Result<TaskImport> {
+        let text = fs_err::read_to_string(file).map_err(|e| anyhow!("not-found: {e}"))?;
+        let cfg = kanban_model::ColumnsToml::load(&self.root);
+        let items = match format {
+            TaskFormat::Taskwarrior => parse_taskwarrior(&text)?,
+            TaskFormat::TodoTxt => parse_todo_txt(&text),
+            TaskFormat::GithubProjectsJson => parse_github_projects(&text, &cfg)?,
+        };
+        let configured = |c: &str| cfg.columns.is_empty() || cfg.columns.iter().any(|x| x.eq_ignore_ascii_case(c));
+        let mut report = TaskImport::default();
+        let mut pending: Vec<(ImportedTask, CardFile, PathBuf)> = vec![];
+        for item in items {
+            let mut task = match item {
+                Ok(t) => t,
+                Err(skipped) => {
+                    report.skipped.push(skipped);
+                    continue;
+                }
+            };
+            let status = task.status.trim().to_lowercase().replace([' ', '_'], "-");
+            let named = cfg.columns.iter().find(|c| c.eq_ignore_ascii_case(&status)).cloned();
+            let target = match (named, status.as_str()) {
+                (Some(c), _) => c,
+                (None, "" | "open" | "todo" | "to-do" | "pending" | "backlog" | "new") => column.to_string(),
+                (None, "in-progress" | "doing" | "started" | "active") if configured("doing") => "doing".into(),
+                (None, "done" | "completed" | "complete" | "closed" | "finished") => "done".into(),
+                _ => {
+                    task.unmapped.push(format!("status {:?} has no matching column; placed in {column}", task.status));
+                    column.to_string()
+                }
+            };
+            let mut card = task.card;
+            let mut dir = self.root.join(".kanban").join(&target);
+            if let Some(policy) = cfg.terminal_policy(&target) {
+                let at = card
+                    .front_matter
+                    .completed_at
+                    .as_deref()
+                    .and_then(|s| kanban_model::parse_since_in(s, cfg.board_offset()))
+                    .unwrap_or_else(kanban_model::now);
+                if policy.stamp_completed_at {
+                    card.front_matter.completed_at = Some(policy.stamp(at));
+                }
+                dir = dir.join(policy.subdir(at));
+            } else {
+                card.front_matter.completed_at = None;
+            }
+            card.front_matter.normalize_ids();
+            let path = dir.join(filename_for(&card.front_matter.id, &card.front_matter.title));
+            let entry = ImportedTask {
+                source: task.source,
+                id: None,
+                title: card.front_matter.title.clone(),
+                column: target,
+                unmapped: task.unmapped,
+            };
+            pending.push((entry, card, path));
+        }
+        if dry_run {
+            report.imported = pending.into_iter().map(|(entry, _, _)| entry).collect();
+            return Ok(report);
+        }
+        let mut written: Vec<(CardFile, String, PathBuf)> = vec![];
+        for (mut entry, mut card, path) in pending {
+            match write_card_atomic(&mut card, &path, &path) {
+                Ok(()) => {
+                    entry.id = Some(card.front_matter.id.clone());
+                    written.push((card, entry.column.clone(), path));
+                    report.imported.push(entry);
+                }
+                Err(e) => report.skipped.push(SkippedTask { source: entry.source, reason: e.to_string() }),
+            }
+        }
+        let entries: Vec<(&CardFile, &str, &Path)> = written.iter().map(|(c, col, p)| (c, col.as_str(), p.as_path())).collect();
+        self.upsert_card_indexes(&entries)?;
+        Ok(report)
+    }
+
+    /// Find `TODO(kanban:<ID>)` markers in the files under `src` and mirror them into a
+    /// `## Code references` section of each referenced open card (`- `path:line` text`, sorted).
+    /// Paths are relative to the board root (absolute when `src` lies outside it). Entries under
+    /// `src` are rebuilt from this scan, entries from other trees are kept, and a section left
+    /// empty is removed. Markers pointing at done-like or unknown cards are reported in `flagged`
+    /// and written nowhere. Hidden entries, `target/` and `node_modules/` are skipped, as are
+    /// files that are not UTF-8.
+    pub fn scan_code_todos(&self, src: &Path, dry_run: bool) -> Result<TodoScan> {
+        use std::collections::BTreeMap;
+        if !src.is_dir() {
+            bail!("not-found: directory {}", src.display());
+        }
+        let re = regex::Regex::new(r"TODO\(kanban:([0-9A-Za-z]+)\)\s*:?\s*(.*)")?;
+        let root = self.root.canonicalize().unwrap_or_else(|_| self.root.clone());
+        let shown = |p: &Path| {
+            let abs = p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+            abs.strip_prefix(&root).map(Path::to_path_buf).unwrap_or(abs).to_string_lossy().replace('\\', "/")
+        };
+        let prefix = shown(src);
+        let under_src = |file: &str| prefix.is_empty() || file == prefix || file.starts_with(&format!("{prefix}/"));
+        let mut report = TodoScan::default();
+        let mut found: BTreeMap<String, Vec<CodeTodo>> = BTreeMap::new();
+        let walker = walkdir::WalkDir::new(src)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| {
+                let name = e.file_name().to_string_lossy();
+                let build_dir = e.file_type().is_dir() && matches!(name.as_ref(), "target" | "node_modules");
+                !name.starts_with('.') && !build_dir
+            });
+        for e in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+            let Ok(text) = fs_err::read_to_string(e.path()) else {
+                continue;
+            };
+            report.files += 1;
+            let file = shown(e.path());
+            for (n, line) in text.lines().enumerate() {
+                for cap in re.captures_iter(line) {
+                    let text = cap[2].trim().trim_end_matches("*/").trim_end_matches("-->").trim();
+                    found.entry(cap[1].to_uppercase()).or_default().push(CodeTodo {
+                        file: file.clone(),
+                        line: n + 1,
+                        text: text.to_string(),
+                    });
+                    report.markers += 1;
+                }
+            }
+        }
+        let done_like = self.done_like();
+        for (id, todos) in &found {
+            let reason = match self.locate_card(id) {
+                None => "missing",
+                Some((_, column)) if done_like.contains(&column) => "done",
+                Some(_) => continue,
+            };
+            for t in todos {
+                report.flagged.push(FlaggedTodo {
+                    id: id.clone(),
+                    file: t.file.clone(),
+                    line: t.line,
+                    reason: reason.into(),
+                });
+            }
+        }
+        report.flagged.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        let mut written: Vec<(CardFile, String, PathBuf)> = vec![];
+        for (path, column) in self.card_files() {
+            if done_like.contains(&column) {
+                continue;
+            }
+            let Ok(mut card) = fs_err::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|t| CardFile::from_markdown(&t))
+            else {
+                continue;
+            };
+            let id = card.front_matter.id.to_uppercase();
+            let (rest, old) = split_code_refs(&card.body);
+            let mut entries: Vec<(String, usize, String)> = old
+                .iter()
+                .filter_map(|e| parse_code_ref(e))
+                .filter(|(file, _, _)| !under_src(file))
+                .collect();
+            entries.extend(found.get(&id).into_iter().flatten().map(|t| (t.file.clone(), t.line, t.text.clone())));
+            entries.sort();
+            entries.dedup();
+            let new: Vec<String> = entries
+                .iter()
+                .map(|(file, line, text)| format!("- `{file}:{line}` {text}").trim_end().to_string())
+                .collect();
+            if new == old {
+                continue;
+            }
+            card.body = if new.is_empty() {
+                format!("{}\n", rest.trim_end())
+            } else if rest.trim().is_empty() {
+                format!("## Code references\n{}\n", new.join("\n"))
+            } else {
+                format!("{}\n\n## Code references\n{}\n", rest.trim_end(), new.join("\n"))
+            };
+            report.updated.push(id);
+            written.push((card, column, path));
+        }
+        report.updated.sort();
+        if !dry_run {
+            for (card, _, path) in &mut written {
+                write_card_atomic(card, path, path)?;
+            }
+            let entries: Vec<_> = written.iter().map(|(c, col, p)| (c, col.as_str(), p.as_path())).collect();
+            self.upsert_card_indexes(&entries)?;
+        }
+        Ok(report)
+    }
+
+    /// Names of the card templates in `.kanban/templates/cards/` (file stems, sorted).
+    pub fn card_template_names(&self) -> Vec<String> {
+        let dir = self.root.join(".kanban").join("templates").join("cards");
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                let stem = name.strip_suffix(".md")?;
+                (!stem.starts_with('.') && e.path().is_file()).then(|| stem.to_string())
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Load `.kanban/templates/cards/<name>.md`.
+    pub fn card_template(&self, name: &str) -> Result<kanban_model::CardTemplate> {
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+        if !valid {
+            bail!("invalid-argument: template name must be letters, digits, '-' or '_' (got {name:?})");
+        }
+        let path = self.root.join(".kanban").join("templates").join("cards").join(format!("{name}.md"));
+        let text = fs_err::read_to_string(&path).map_err(|_| {
+            let known = self.card_template_names();
+            anyhow!(
+                "not-found: template {name} (known: {})",
+                if known.is_empty() { "none".to_string() } else { known.join(", ") }
+            )
+        })?;
+        kanban_model::CardTemplate::parse(&text).map_err(|e| anyhow!("invalid-argument: template {name}: {e}"))
+    }
+
+    /// Card files (`<ULID>__<slug>.md`) with their column, skipping templates/generated/notes and `.trash/`.
+    pub fn card_files(&self) -> impl Iterator<Item = (PathBuf, String)> {
+        let root = self.root.join(".kanban");
+        walkdir::WalkDir::new(&root)
+            .min_depth(2)
+            .into_iter()
+            .filter_entry(is_card_entry)
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(move |e| {
+                let name = e.file_name().to_string_lossy();
+                if !name.contains("__") || !name.to_ascii_lowercase().ends_with(".md") {
+                    return None;
+                }
+                let column = e
+                    .path()
+                    .strip_prefix(&root)
+                    .ok()?
+                    .components()
+                    .next()?
+                    .as_os_str()
+                    .to_string_lossy()
+                    .to_string();
+                if matches!(column.as_str(), "templates" | "generated" | "notes" | "history" | ARCHIVE_DIR | ATTACHMENTS_DIR) {
+                    return None;
+                }
+                Some((e.path().to_path_buf(), column))
+            })
+    }
+
+    fn find_path_by_id(&self, id: &str) -> Result<(PathBuf, kanban_model::CardFrontMatter)> {
+        let root = self.root.join(".kanban");
+        if !root.exists() {
+            bail!(".kanban not found: {}", root.display());
+        }
+        for entry in walkdir::WalkDir::new(&root)
+            .into_iter()
+            .filter_entry(is_card_entry)
+            .filter_map(|e| e.ok())
+        {
+            if entry.file_type().is_file() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if let Some((fid, _)) = name.split_once("__") {
+                    if fid.eq_ignore_ascii_case(id) {
+                        let text = fs_err::read_to_string(entry.path())?;
+                        let cf = CardFile::from_markdown(&text)?;
+                        return Ok((entry.path().to_path_buf(), cf.front_matter));
+                    }
+                }
+            }
+        }
+        bail!("card not found: {}", id)
+    }
+}
+
+#[cfg(test)]
+mod tests_notes_storage {
+    use super::*;
+    use tempfile::tempdir;
 
     #[test]
     fn append_and_list_basic_and_limit() {
@@ -375,6 +1644,9 @@ mod tests_notes_storage {
                 text: format!("entry-{i}"),
                 tags: None,
                 author: None,
+                decision: None,
+                key: None,
+                summary: None,
             };
             b.append_note(id, &e).unwrap();
         }
@@ -385,25 +1657,1406 @@ mod tests_notes_storage {
         let v2 = b.list_notes(id, Some(10), true).unwrap();
         assert!(v2.len() >= 4);
     }
+
+    #[test]
+    fn list_notes_range_compares_instants_not_strings() {
+        let tmp = tempdir().unwrap();
+        let b = Board::new(tmp.path());
+        let id = "01TESTNOTE0000000000000001";
+        // 09:00+09:00 == 00:00Z, which sorts after "01:00Z" as text but is earlier in time
+        for (ts, text) in [("2025-01-01T09:00:00+09:00", "tokyo"), ("2025-01-01T01:00:00Z", "utc"), ("garbage", "bad")] {
+            let e = kanban_model::NoteEntry {
+                ts: ts.into(),
+                type_: "worklog".into(),
+                text: text.into(),
+                tags: None,
+                author: None,
+                decision: None,
+                key: None,
+                summary: None,
+            };
+            b.append_note(id, &e).unwrap();
+        }
+        let at = |s: &str| time::OffsetDateTime::parse(s, &time::format_description::well_known::Rfc3339).unwrap();
+        let texts = |v: Vec<kanban_model::NoteEntry>| v.into_iter().map(|n| n.text).collect::<Vec<_>>();
+        let after = b.list_notes_advanced(id, None, true, Some(at("2025-01-01T00:30:00Z")), None).unwrap();
+        assert_eq!(texts(after), vec!["utc"]);
+        let before = b.list_notes_advanced(id, None, true, None, Some(at("2025-01-01T00:30:00Z"))).unwrap();
+        assert_eq!(texts(before), vec!["tokyo"]);
+        assert_eq!(b.list_notes_advanced(id, None, true, None, None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn import_markdown_dir_creates_cards_and_handles_originals() {
+        let tmp = tempdir().unwrap();
+        let b = Board::new(tmp.path());
+        let src = tmp.path().join("todo");
+        fs_err::create_dir_all(src.join("sub")).unwrap();
+        fs_err::write(src.join("a.md"), "intro\n# Fix login\nsteps\n").unwrap();
+        fs_err::write(src.join("no-heading.md"), "just text\n").unwrap();
+        fs_err::write(src.join("empty.md"), "  \n").unwrap();
+        fs_err::write(src.join(".hidden.md"), "# Hidden\n").unwrap();
+        fs_err::write(src.join("notes.txt"), "# Not markdown\n").unwrap();
+        fs_err::write(src.join("sub").join("deep.md"), "# Deep\n").unwrap();
+
+        let dry = b.import_markdown_dir(&src, "backlog", false, ImportOriginals::Keep, true).unwrap();
+        let titles: Vec<_> = dry.imported.iter().map(|f| f.title.as_str()).collect();
+        assert_eq!(titles, vec!["Fix login", "no-heading"]);
+        assert!(dry.imported.iter().all(|f| f.id.is_none()));
+        assert_eq!(dry.skipped, vec![SkippedFile { file: "empty.md".into(), reason: "empty file".into() }]);
+        assert!(!tmp.path().join(".kanban").exists());
+
+        let r = b.import_markdown_dir(&src, "backlog", true, ImportOriginals::Link, false).unwrap();
+        assert_eq!(r.imported.len(), 3);
+        assert_eq!(r.imported[2].file, "sub/deep.md");
+        let card = b.read_card(r.imported[0].id.as_deref().unwrap()).unwrap();
+        assert_eq!(card.front_matter.title, "Fix login");
+        assert_eq!(card.body.trim_end(), "intro\n# Fix login\nsteps");
+        let link = &card.front_matter.links.as_ref().unwrap()[0];
+        assert!(link.url.starts_with("file://") && link.url.ends_with("/todo/a.md"));
+        assert!(src.join("a.md").exists());
+
+        let r = b.import_markdown_dir(&src.join("sub"), "doing", false, ImportOriginals::Move, false).unwrap();
+        assert_eq!(r.imported.len(), 1);
+        assert!(!src.join("sub").join("deep.md").exists());
+        assert!(b.read_card(r.imported[0].id.as_deref().unwrap()).unwrap().front_matter.links.is_none());
+        // the board's own cards are never re-imported
+        let again = b.import_markdown_dir(tmp.path(), "backlog", true, ImportOriginals::Keep, true).unwrap();
+        assert!(again.imported.iter().all(|f| !f.file.starts_with(".kanban")));
+    }
+
+    #[test]
+    fn import_tasks_converts_foreign_exports_and_reports_unmapped_data() {
+        let tmp = tempdir().unwrap();
+        let b = Board::new(tmp.path());
+        let tw = tmp.path().join("tw.json");
+        fs_err::write(
+            &tw,
+            r#"[{"uuid":"a1","description":"Fix login","status":"pending","priority":"H","tags":["backend"],"project":"auth","due":"20250701T000000Z","depends":"b2","annotations":[{"entry":"20250602T090000Z","description":"seen on staging"}]},
+                {"uuid":"b2","description":"Started","status":"pending","start":"20250603T000000Z"},
+                {"uuid":"c3","description":"Shipped","status":"completed","end":"20250515T120000Z"},
+                {"uuid":"d4","description":"Gone","status":"deleted"}]"#,
+        )
+        .unwrap();
+        let r = b.import_tasks(&tw, TaskFormat::Taskwarrior, "backlog", false).unwrap();
+        let placed: Vec<(&str, &str)> = r.imported.iter().map(|t| (t.title.as_str(), t.column.as_str())).collect();
+        assert_eq!(placed, [("Fix login", "backlog"), ("Started", "doing"), ("Shipped", "done")]);
+        assert_eq!(r.imported[0].unmapped, ["depends: b2"]);
+        assert_eq!(r.skipped, [SkippedTask { source: "d4".into(), reason: "deleted in taskwarrior".into() }]);
+        let card = b.read_card(r.imported[0].id.as_deref().unwrap()).unwrap();
+        assert_eq!(card.front_matter.priority.as_deref(), Some("P1"));
+        assert_eq!(card.front_matter.lane.as_deref(), Some("auth"));
+        assert_eq!(card.front_matter.due_date.as_deref(), Some("2025-07-01T00:00:00Z"));
+        assert!(card.body.contains("- 2025-06-02: seen on staging"));
+        let (path, _) = b.locate_card(r.imported[2].id.as_deref().unwrap()).unwrap();
+        assert!(path.starts_with(tmp.path().join(".kanban/done/2025/05")), "{}", path.display());
+
+        let todo = tmp.path().join("todo.txt");
+        fs_err::write(&todo, "(A) 2025-05-01 Call Mom +family @phone due:2025-07-02\nx 2025-06-01 Pay rent pri:B\n(F) Someday url:x\n\n+only @tags\n").unwrap();
+        let r = b.import_tasks(&todo, TaskFormat::TodoTxt, "backlog", true).unwrap();
+        assert!(r.imported.iter().all(|t| t.id.is_none()));
+        assert_eq!(r.imported[1].column, "done");
+        assert_eq!(r.imported[2].unmapped, ["tag url:x", "priority (F) has no P0-P3 equivalent"]);
+        assert_eq!(r.skipped[0].source, "line 5");
+        let r = b.import_tasks(&todo, TaskFormat::TodoTxt, "backlog", false).unwrap();
+        let fm = b.read_card(r.imported[0].id.as_deref().unwrap()).unwrap().front_matter;
+        assert_eq!((fm.title.as_str(), fm.priority.as_deref(), fm.lane.as_deref()), ("Call Mom", Some("P0"), Some("family")));
+        assert_eq!(fm.labels.as_deref(), Some(&["phone".to_string()][..]));
+        assert_eq!(fm.due_date.as_deref(), Some("2025-07-02"));
+
+        let gh = tmp.path().join("gh.json");
+        fs_err::write(
+            &gh,
+            r#"{"items":[{"id":"PVTI_1","title":"Add search","status":"In Progress","Priority":"High","labels":["feature"],"assignees":["octocat"],"milestone":{"title":"v1"},"content":{"number":12,"repository":"o/r","url":"https://github.com/o/r/issues/12","body":"Details"}},
+                {"id":"PVTI_2","title":"Blocked thing","status":"Blocked"}],"totalCount":2}"#,
+        )
+        .unwrap();
+        let r = b.import_tasks(&gh, TaskFormat::GithubProjectsJson, "backlog", false).unwrap();
+        assert_eq!(r.imported[0].column, "doing");
+        assert_eq!(r.imported[0].unmapped, [r#"milestone: {"title":"v1"}"#]);
+        assert_eq!(r.imported[1].unmapped, [r#"status "Blocked" has no matching column; placed in backlog"#]);
+        let card = b.read_card(r.imported[0].id.as_deref().unwrap()).unwrap();
+        assert_eq!(card.front_matter.priority.as_deref(), Some("P1"));
+        assert_eq!(card.front_matter.links.as_ref().unwrap()[0].title.as_deref(), Some("o/r#12"));
+        assert_eq!(card.body.trim_end(), "Details");
+        assert!(b.import_tasks(&todo, TaskFormat::GithubProjectsJson, "backlog", true).is_err());
+        assert_eq!(TaskFormat::parse("todo.txt"), Some(TaskFormat::TodoTxt));
+    }
+
+    #[test]
+    fn scan_code_todos_mirrors_markers_into_cards_and_flags_stale_ones() {
+        let tmp = tempdir().unwrap();
+        let b = Board::new(tmp.path());
+        let a = b.new_card("A", None, None, None, "backlog", None, None, None).unwrap();
+        let d = b.new_card("D", None, None, None, "backlog", None, None, None).unwrap();
+        b.done_card(&d).unwrap();
+        let src = tmp.path().join("src");
+        fs_err::create_dir_all(src.join("target")).unwrap();
+        fs_err::write(
+            src.join("lib.rs"),
+            format!("fn x() {{}}\n// TODO(kanban:{}): retry on timeout\n/* TODO(kanban:{d}) */\n// TODO(kanban:01NOPE)\n", a.to_lowercase()),
+        )
+        .unwrap();
+        fs_err::write(src.join("target").join("gen.rs"), format!("// TODO(kanban:{a}) generated\n")).unwrap();
+        let mut card = b.read_card(&a).unwrap();
+        card.body = "Intro\n\n## Code references\n- `elsewhere/x.py:3` keep me\n- `src/old.rs:1` gone\n".into();
+        let (path, _) = b.locate_card(&a).unwrap();
+        write_card_atomic(&mut card, &path, &path).unwrap();
+
+        let dry = b.scan_code_todos(&src, true).unwrap();
+        assert_eq!((dry.files, dry.markers), (1, 3));
+        assert_eq!(dry.updated, vec![a.clone()]);
+        assert!(b.read_card(&a).unwrap().body.contains("src/old.rs"));
+
+        let r = b.scan_code_todos(&src, false).unwrap();
+        let reasons: Vec<_> = r.flagged.iter().map(|f| (f.id.as_str(), f.line, f.reason.as_str())).collect();
+        assert_eq!(reasons, vec![(d.as_str(), 3, "done"), ("01NOPE", 4, "missing")]);
+        assert_eq!(
+            b.read_card(&a).unwrap().body.trim_end(),
+            "Intro\n\n## Code references\n- `elsewhere/x.py:3` keep me\n- `src/lib.rs:2` retry on timeout"
+        );
+        assert!(b.scan_code_todos(&src, false).unwrap().updated.is_empty(), "rescan is a no-op");
+
+        fs_err::write(src.join("lib.rs"), "fn x() {}\n").unwrap();
+        b.scan_code_todos(&src, false).unwrap();
+        assert_eq!(b.read_card(&a).unwrap().body.trim_end(), "Intro\n\n## Code references\n- `elsewhere/x.py:3` keep me");
+    }
+
+    #[test]
+    fn card_relation_records_fold_over_edges_and_compact() {
+        let tmp = tempdir().unwrap();
+        let b = Board::new(tmp.path());
+        let base = tmp.path().join(".kanban");
+        fs_err::create_dir_all(&base).unwrap();
+        fs_err::write(
+            base.join("relations.ndjson"),
+            "{\"type\":\"parent\",\"from\":\"A\",\"to\":\"P\"}\n{\"type\":\"depends\",\"from\":\"B\",\"to\":\"A\"}\n",
+        )
+        .unwrap();
+        b.set_card_relations("a", &[RelationEdge::new("relates", "A", "C")]).unwrap();
+        b.set_card_relations("B", &[]).unwrap();
+        assert_eq!(b.read_relations_index().unwrap(), vec![RelationEdge::new("relates", "A", "C")]);
+        let text = fs_err::read_to_string(base.join("relations.ndjson")).unwrap();
+        assert_eq!(text.lines().count(), 4, "updates append instead of rewriting");
+        assert_eq!(fs_err::read_to_string(base.join(RELATIONS_RECORDS_FILE)).unwrap(), "2");
+
+        assert_eq!(b.compact_relations_index().unwrap(), 1);
+        let text = fs_err::read_to_string(base.join("relations.ndjson")).unwrap();
+        assert_eq!(text, "{\"type\":\"relates\",\"from\":\"A\",\"to\":\"C\"}\n");
+        assert!(!base.join(RELATIONS_RECORDS_FILE).exists());
+
+        // The sidecar count alone triggers compaction once records pile up
+        fs_err::write(base.join(RELATIONS_RECORDS_FILE), (RELATIONS_COMPACT_AFTER - 1).to_string()).unwrap();
+        b.set_card_relations("B", &[RelationEdge::new("depends", "B", "A")]).unwrap();
+        let text = fs_err::read_to_string(base.join("relations.ndjson")).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(!text.contains("\"card\""));
+        assert!(!base.join(RELATIONS_RECORDS_FILE).exists());
+    }
+
+    #[test]
+    fn normalize_id_case_fixes_files_notes_and_indexes() {
+        let tmp = tempdir().unwrap();
+        let b = Board::new(tmp.path());
+        let a = b.new_card("A", None, None, None, "backlog", None, None, None).unwrap();
+        let c = b.new_card("C", None, None, None, "backlog", None, None, None).unwrap();
+        let (p, _) = b.find_path_by_id(&a).unwrap();
+        let mut card = CardFile::from_markdown(&fs_err::read_to_string(&p).unwrap()).unwrap();
+        card.front_matter.id = a.to_lowercase();
+        card.front_matter.parent = Some(c.to_lowercase());
+        let name = p.file_name().unwrap().to_string_lossy().to_string();
+        let lower = p.with_file_name(name.replacen(&a, &a.to_lowercase(), 1));
+        fs_err::remove_file(&p).unwrap();
+        fs_err::write(&lower, card.to_markdown().unwrap()).unwrap();
+        let notes = tmp.path().join(".kanban").join("notes");
+        fs_err::create_dir_all(&notes).unwrap();
+        fs_err::write(notes.join(format!("{}.ndjson", a.to_lowercase())), "{\"ts\":\"t\",\"type\":\"worklog\",\"text\":\"x\"}\n").unwrap();
+
+        let dry = b.normalize_id_case(true).unwrap();
+        assert_eq!(dry.files_renamed.len(), 1);
+        assert!(lower.exists());
+        let r = b.normalize_id_case(false).unwrap();
+        assert_eq!(r.cards_rewritten, vec![a.clone()]);
+        assert_eq!(r.notes_renamed.len(), 1);
+        assert!(p.exists() && !lower.exists());
+        assert_eq!(b.read_card(&a).unwrap().front_matter.parent, Some(c.clone()));
+        assert_eq!(b.list_notes(&a, None, true).unwrap().len(), 1);
+        assert_eq!(b.read_relations_index().unwrap(), vec![RelationEdge::new("parent", &a, &c)]);
+        assert!(b.normalize_id_case(false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn verify_relations_index_reports_missing_and_stale() {
+        let tmp = tempdir().unwrap();
+        let b = Board::new(tmp.path());
+        let a = b.new_card("A", None, None, None, "backlog", None, None, None).unwrap();
+        let c = b.new_card("C", None, None, None, "backlog", None, None, None).unwrap();
+        let p = b.find_path_by_id(&a).unwrap().0;
+        let mut card = CardFile::from_markdown(&fs_err::read_to_string(&p).unwrap()).unwrap();
+        card.front_matter.depends_on = Some(vec![c.to_lowercase()]);
+        fs_err::write(&p, card.to_markdown().unwrap()).unwrap();
+        b.reindex_relations().unwrap();
+        assert!(b.verify_relations_index().unwrap().is_empty());
+        let idx = tmp.path().join(".kanban").join("relations.ndjson");
+        fs_err::write(&idx, format!("{{\"type\":\"relates\",\"from\":\"{a}\",\"to\":\"{c}\"}}\n")).unwrap();
+        let d = b.verify_relations_index().unwrap();
+        assert_eq!(d.missing, vec![RelationEdge::new("depends", &a, &c)]);
+        assert_eq!(d.stale, vec![RelationEdge::new("relates", &a, &c)]);
+    }
+
+    #[test]
+    fn write_card_atomic_moves_with_new_content_and_leaves_one_file() {
+        let tmp = tempdir().unwrap();
+        let b = Board::new(tmp.path());
+        let a = b.new_card("Old", None, None, None, "backlog", None, None, None).unwrap();
+        let other = b.new_card("Other", None, None, None, "backlog", None, None, None).unwrap();
+        let from = b.find_path_by_id(&a).unwrap().0;
+        let mut card = b.read_card(&a).unwrap();
+        card.front_matter.title = "New".into();
+        let to = from.with_file_name(filename_for(&a, "New"));
+        write_card_atomic(&mut card, &from, &to).unwrap();
+        assert!(!from.exists());
+        assert_eq!(b.read_card(&a).unwrap().front_matter.title, "New");
+        let names: Vec<String> = fs_err::read_dir(to.parent().unwrap())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names.len(), 2, "{names:?}");
+        // never clobber another card
+        let occupied = b.find_path_by_id(&other).unwrap().0;
+        let e = write_card_atomic(&mut card, &to, &occupied).unwrap_err().to_string();
+        assert!(e.starts_with("conflict:"), "{e}");
+        assert_eq!(b.read_card(&a).unwrap().front_matter.title, "New");
+    }
+
+    #[test]
+    fn clone_layout_copies_config_templates_and_selected_cards() {
+        let src = tempdir().unwrap();
+        let dst = tempdir().unwrap();
+        let b = Board::new(src.path());
+        let kb = src.path().join(".kanban");
+        fs_err::create_dir_all(kb.join("templates")).unwrap();
+        fs_err::write(kb.join("columns.toml"), "columns = [\"todo\", \"doing\"]\n").unwrap();
+        fs_err::write(kb.join("templates/card.md"), "## Why\n").unwrap();
+        let checklist = b
+            .new_card("Release checklist", None, None, None, "doing", Some(vec!["recurring".into()]), None, Some("- [ ] tag".into()))
+            .unwrap();
+        b.new_card("Feature work", None, None, None, "todo", None, None, None).unwrap();
+        let picked = b.new_card("Onboarding", None, None, None, "todo", None, None, None).unwrap();
+        b.done_card(&picked).unwrap();
+
+        let report = b.clone_layout_into(dst.path(), std::slice::from_ref(&picked), &["recurring".into()]).unwrap();
+        assert_eq!(report.files, vec!["columns.toml", "templates/card.md"]);
+        assert_eq!(report.cards.len(), 2);
+        let copy = report.cards.iter().find(|c| c.from == checklist).unwrap();
+        assert_ne!(copy.id, checklist);
+        assert_eq!(copy.column, "doing");
+        let done_copy = report.cards.iter().find(|c| c.from == picked).unwrap();
+        assert_eq!(done_copy.column, "todo");
+        let nb = Board::new(dst.path());
+        let card = nb.read_card(&copy.id).unwrap();
+        assert_eq!(card.body.trim(), "- [ ] tag");
+        assert!(nb.read_card(&done_copy.id).unwrap().front_matter.completed_at.is_none());
+        assert!(dst.path().join(".kanban/todo").is_dir());
+
+        let e = b.clone_layout_into(dst.path(), &[], &[]).unwrap_err().to_string();
+        assert!(e.starts_with("conflict:"), "{e}");
+    }
+
+    #[test]
+    fn query_filters_index_rows_and_card_files_alike() {
+        let tmp = tempdir().unwrap();
+        let b = Board::new(tmp.path());
+        let a = b
+            .new_card("Fix login", None, Some("P1".into()), None, "backlog", Some(vec!["Bug".into()]), None, Some("token expiry".into()))
+            .unwrap();
+        b.new_card("Write docs", None, None, None, "backlog", None, None, None).unwrap();
+        let labelled = ListFilter { label: Some("bug".into()), ..Default::default() };
+        let (hits, stats) = b.query_with_stats(&labelled).unwrap();
+        assert_eq!(stats.source, QuerySource::Index);
+        assert_eq!(hits.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), vec![a.as_str()]);
+        assert_eq!(hits[0].priority.as_deref(), Some("P1"));
+
+        // a body substring needs the card files; the same filters still apply
+        let by_body = ListFilter { query: Some("EXPIRY".into()), ..labelled.clone() };
+        let (hits, stats) = b.query_with_stats(&by_body).unwrap();
+        assert_eq!(stats.source, QuerySource::FsScan);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].path.as_deref().is_some_and(|p| p.starts_with(".kanban/backlog/")));
+        let sn = hits[0].snippet.as_ref().unwrap();
+        assert_eq!((sn.text.as_str(), sn.offset), ("token **expiry**", 6));
+
+        // without an index everything comes from the card files
+        fs_err::remove_file(tmp.path().join(".kanban/cards.ndjson")).unwrap();
+        let (hits, stats) = b.query_with_stats(&labelled).unwrap();
+        assert_eq!(stats.source, QuerySource::FsScan);
+        assert!(stats.index_missing);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(b.query(&ListFilter::default()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn done_index_serves_resolution_and_date_queries() {
+        let tmp = tempdir().unwrap();
+        let b = Board::new(tmp.path());
+        let a = b.new_card("Ship it", None, None, None, "backlog", Some(vec!["ui".into()]), None, None).unwrap();
+        let c = b.new_card("Dupe", None, None, None, "backlog", None, None, None).unwrap();
+        b.done_card(&a).unwrap();
+        let (path, _) = b.find_path_by_id(&c).unwrap();
+        let mut card = b.read_card(&c).unwrap();
+        card.front_matter.resolution = Some("duplicate".into());
+        fs_err::write(&path, card.to_markdown().unwrap()).unwrap();
+        b.done_card(&c).unwrap();
+        let done = b.read_done_index().unwrap();
+        assert_eq!(done.len(), 2);
+        assert!(done.iter().all(|r| r.column == "done" && r.completed_at.is_some()));
+
+        let dupes = ListFilter { resolution: Some("Duplicate".into()), ..Default::default() };
+        let (hits, stats) = b.query_with_stats(&dupes).unwrap();
+        assert!(stats.done_index);
+        assert_eq!(hits.iter().map(|h| h.id.as_str()).collect::<Vec<_>>(), vec![c.as_str()]);
+        let since = ListFilter { completed_since: Some(OffsetDateTime::now_utc() - time::Duration::hours(1)), ..Default::default() };
+        assert_eq!(b.query(&since).unwrap().len(), 2);
+
+        // reopening appends a tombstone; reindex compacts to one row per done card
+        b.move_card(&c, "doing").unwrap();
+        assert_eq!(b.read_done_index().unwrap().len(), 1);
+        assert!(b.query(&dupes).unwrap().is_empty());
+        assert_eq!(b.reindex_done().unwrap(), 1);
+        let text = fs_err::read_to_string(tmp.path().join(".kanban/done.ndjson")).unwrap();
+        assert_eq!(text.lines().count(), 1);
+    }
+}
+
+/// Result of [`Board::verify_cards_index`] (upper-cased ids).
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct CardsIndexDrift {
+    /// Card files without a cards.ndjson row
+    pub missing: Vec<String>,
+    /// Rows whose card file is gone
+    pub stale: Vec<String>,
+    /// Rows pointing at another column or path than the file
+    pub moved: Vec<String>,
+}
+
+impl CardsIndexDrift {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.stale.is_empty() && self.moved.is_empty()
+    }
+}
+
+/// Result of [`Board::compact`]; paths are relative to the board root.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct CompactReport {
+    /// Card files re-filed into their partition: (from, to)
+    pub moves: Vec<(String, String)>,
+    /// Cards left in place because the destination already exists
+    pub skipped: Vec<String>,
+    /// Empty directories removed, relative to `.kanban/`
+    pub removed_dirs: Vec<String>,
+    /// Edges kept in relations.ndjson
+    pub relations: usize,
+    /// Rows in done.ndjson
+    pub done: usize,
+}
+
+/// Result of [`Board::normalize_id_case`].
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct IdCaseReport {
+    /// Cards whose front matter ids were uppercased
+    pub cards_rewritten: Vec<String>,
+    /// Card files renamed to an uppercase id prefix
+    pub files_renamed: Vec<String>,
+    /// Notes files renamed (or merged) to an uppercase id
+    pub notes_renamed: Vec<String>,
+}
+
+impl IdCaseReport {
+    pub fn is_empty(&self) -> bool {
+        self.cards_rewritten.is_empty() && self.files_renamed.is_empty() && self.notes_renamed.is_empty()
+    }
+}
+
+/// What [`Board::import_markdown_dir`] does with a source file after its card is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportOriginals {
+    /// Leave the file untouched
+    #[default]
+    Keep,
+    /// Delete the file; the card is now the only copy
+    Move,
+    /// Leave the file and add a `file://` link to it in the card's `links`
+    Link,
+}
+
+/// Result of [`Board::import_markdown_dir`]; paths are relative to the imported directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MarkdownImport {
+    pub imported: Vec<ImportedFile>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportedFile {
+    pub file: String,
+    /// New card id (absent on a dry run)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SkippedFile {
+    pub file: String,
+    pub reason: String,
+}
+
+/// Export formats [`Board::import_tasks`] understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskFormat {
+    /// `task export` (JSON array of tasks)
+    Taskwarrior,
+    /// todo.txt, one task per line
+    TodoTxt,
+    /// `gh project item-list --format json`
+    GithubProjectsJson,
+}
+
+impl TaskFormat {
+    pub const NAMES: [&'static str; 3] = ["taskwarrior", "todo-txt", "github-projects-json"];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "taskwarrior" => Some(Self::Taskwarrior),
+            "todo-txt" | "todo.txt" | "todotxt" => Some(Self::TodoTxt),
+            "github-projects-json" | "github-projects" => Some(Self::GithubProjectsJson),
+            _ => None,
+        }
+    }
+}
+
+/// Result of [`Board::import_tasks`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TaskImport {
+    pub imported: Vec<ImportedTask>,
+    pub skipped: Vec<SkippedTask>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportedTask {
+    /// Where the item came from: `line 3`, a taskwarrior uuid, a project item id
+    pub source: String,
+    /// New card id (absent on a dry run)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub title: String,
+    pub column: String,
+    /// Source data the card could not represent
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unmapped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SkippedTask {
+    pub source: String,
+    pub reason: String,
+}
+
+/// One converted item before it is placed: its status as the source tool names it, and the card.
+struct ForeignTask {
+    source: String,
+    status: String,
+    card: CardFile,
+    unmapped: Vec<String>,
+}
+
+/// Short display of a source value for `unmapped`.
+fn unmapped_value(v: &serde_json::Value) -> String {
+    let s = match v {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if s.chars().count() > 60 {
+        format!("{}…", s.chars().take(59).collect::<String>())
+    } else {
+        s
+    }
+}
+
+fn string_list(v: Option<&serde_json::Value>) -> Vec<String> {
+    v.and_then(|x| x.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|x| x.as_str())
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+/// `task export` output: a JSON array (one object per line is accepted too).
+fn parse_taskwarrior(text: &str) -> Result<Vec<std::result::Result<ForeignTask, SkippedTask>>> {
+    use serde_json::Value;
+    let tasks: Vec<Value> = match serde_json::from_str::<Value>(text.trim()) {
+        Ok(Value::Array(a)) => a,
+        Ok(v @ Value::Object(_)) => vec![v],
+        _ => text
+            .lines()
+            .map(|l| l.trim().trim_end_matches(','))
+            .filter(|l| !l.is_empty() && *l != "[" && *l != "]")
+            .map(serde_json::from_str::<Value>)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| anyhow!("invalid-argument: not a taskwarrior export: {e}"))?,
+    };
+    // taskwarrior's compact timestamps (20250701T120000Z) as RFC3339
+    let when = |v: &Value| {
+        let fmt = time::macros::format_description!("[year][month][day]T[hour][minute][second]Z");
+        let at = time::PrimitiveDateTime::parse(v.as_str()?, fmt).ok()?.assume_utc();
+        at.format(&Rfc3339).ok()
+    };
+    const HANDLED: [&str; 14] = [
+        "id", "uuid", "description", "status", "priority", "tags", "project", "due", "end", "start", "entry",
+        "modified", "urgency", "annotations",
+    ];
+    let mut out = vec![];
+    for (i, t) in tasks.iter().enumerate() {
+        let source = t.get("uuid").and_then(|v| v.as_str()).map_or_else(|| format!("task {}", i + 1), |u| u.to_string());
+        let status = t.get("status").and_then(|v| v.as_str()).unwrap_or("pending");
+        let title = t.get("description").and_then(|v| v.as_str()).unwrap_or("").trim();
+        let skip = match status {
+            "deleted" => Some("deleted in taskwarrior"),
+            "recurring" => Some("recurring template (its pending instances are imported)"),
+            _ if title.is_empty() => Some("no description"),
+            _ => None,
+        };
+        if let Some(reason) = skip {
+            out.push(Err(SkippedTask { source, reason: reason.into() }));
+            continue;
+        }
+        let mut card = CardFile::new_with_title(title);
+        let mut unmapped = vec![];
+        let fm = &mut card.front_matter;
+        match t.get("priority").and_then(|v| v.as_str()) {
+            Some("H") => fm.priority = Some("P1".into()),
+            Some("M") => fm.priority = Some("P2".into()),
+            Some("L") => fm.priority = Some("P3".into()),
+            Some(p) => unmapped.push(format!("priority {p}")),
+            None => {}
+        }
+        let tags = string_list(t.get("tags"));
+        fm.labels = (!tags.is_empty()).then_some(tags);
+        fm.lane = t.get("project").and_then(|v| v.as_str()).map(|s| s.to_string());
+        fm.due_date = t.get("due").and_then(when);
+        let status = match status {
+            "completed" => {
+                fm.completed_at = t.get("end").and_then(when);
+                "done"
+            }
+            "pending" if t.get("start").is_some() => "in-progress",
+            other => other,
+        };
+        let notes: Vec<String> = t
+            .get("annotations")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|a| {
+                let text = a.get("description")?.as_str()?;
+                let day = a.get("entry").and_then(when).map(|w| w.chars().take(10).collect::<String>());
+                Some(match day {
+                    Some(d) => format!("- {d}: {text}"),
+                    None => format!("- {text}"),
+                })
+            })
+            .collect();
+        if !notes.is_empty() {
+            card.body = format!("## Annotations\n{}\n", notes.join("\n"));
+        }
+        if let Some(o) = t.as_object() {
+            for (k, v) in o.iter().filter(|(k, _)| !HANDLED.contains(&k.as_str())) {
+                unmapped.push(format!("{k}: {}", unmapped_value(v)));
+            }
+        }
+        out.push(Ok(ForeignTask { source, status: status.into(), card, unmapped }));
+    }
+    Ok(out)
+}
+
+/// todo.txt: `x` marks done (with an optional completion date), `(A)`-`(D)` map to P0-P3,
+/// `+project` is the lane (further projects become labels), `@context` a label and `due:` the
+/// due date. Other `key:value` tags are reported as unmapped.
+fn parse_todo_txt(text: &str) -> Vec<std::result::Result<ForeignTask, SkippedTask>> {
+    let is_date = |w: &str| time::Date::parse(w, time::macros::format_description!("[year]-[month]-[day]")).is_ok();
+    let mut out = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let source = format!("line {}", i + 1);
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let mut done = false;
+        let mut completed = None;
+        let mut priority = None;
+        if words[0] == "x" {
+            done = true;
+            words.remove(0);
+            if words.first().is_some_and(|w| is_date(w)) {
+                completed = Some(words.remove(0).to_string());
+            }
+        } else if let Some(p) = words[0].strip_prefix('(').and_then(|w| w.strip_suffix(')')).filter(|p| p.len() == 1 && p.chars().all(|c| c.is_ascii_uppercase())) {
+            priority = Some(p.to_string());
+            words.remove(0);
+        }
+        // creation date
+        if words.first().is_some_and(|w| is_date(w)) {
+            words.remove(0);
+        }
+        let mut card = CardFile::new_with_title("");
+        let mut unmapped = vec![];
+        let mut title = vec![];
+        let mut labels: Vec<String> = vec![];
+        for w in words {
+            if let Some(p) = w.strip_prefix('+').filter(|p| !p.is_empty()) {
+                if card.front_matter.lane.is_none() {
+                    card.front_matter.lane = Some(p.to_string());
+                } else {
+                    unmapped.push(format!("project +{p} kept as a label"));
+                    labels.push(p.to_string());
+                }
+            } else if let Some(c) = w.strip_prefix('@').filter(|c| !c.is_empty()) {
+                if !labels.iter().any(|l| l == c) {
+                    labels.push(c.to_string());
+                }
+            } else if let Some((k, v)) = w.split_once(':').filter(|(k, v)| !k.is_empty() && !v.is_empty() && !v.starts_with("//") && !v.contains(':')) {
+                match k {
+                    "due" if kanban_model::parse_due_date(v).is_some() => card.front_matter.due_date = Some(v.to_string()),
+                    "pri" if done && priority.is_none() && v.len() == 1 => priority = Some(v.to_ascii_uppercase()),
+                    _ => unmapped.push(format!("tag {w}")),
+                }
+            } else {
+                title.push(w);
+            }
+        }
+        if title.is_empty() {
+            out.push(Err(SkippedTask { source, reason: "no task text".into() }));
+            continue;
+        }
+        card.front_matter.title = title.join(" ");
+        card.front_matter.priority = match priority.as_deref() {
+            None => None,
+            Some("A") => Some("P0".into()),
+            Some("B") => Some("P1".into()),
+            Some("C") => Some("P2".into()),
+            Some("D") => Some("P3".into()),
+            Some(p) => {
+                unmapped.push(format!("priority ({p}) has no P0-P3 equivalent"));
+                None
+            }
+        };
+        card.front_matter.labels = (!labels.is_empty()).then_some(labels);
+        card.front_matter.completed_at = completed;
+        let status = if done { "done" } else { "open" };
+        out.push(Ok(ForeignTask { source, status: status.into(), card, unmapped }));
+    }
+    out
+}
+
+/// `gh project item-list --format json`: `{ items: [...] }` (a bare array is accepted too).
+/// Field names are matched case-insensitively; the issue or PR body becomes the card body and its
+/// URL a link.
+fn parse_github_projects(text: &str, cfg: &kanban_model::ColumnsToml) -> Result<Vec<std::result::Result<ForeignTask, SkippedTask>>> {
+    use serde_json::Value;
+    let doc: Value = serde_json::from_str(text).map_err(|e| anyhow!("invalid-argument: not GitHub Projects JSON: {e}"))?;
+    let items = match &doc {
+        Value::Array(a) => a.clone(),
+        _ => doc
+            .get("items")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .ok_or_else(|| anyhow!("invalid-argument: GitHub Projects JSON has no items array"))?,
+    };
+    let mut out = vec![];
+    for (i, item) in items.iter().enumerate() {
+        let source = item.get("id").and_then(|v| v.as_str()).map_or_else(|| format!("item {}", i + 1), |s| s.to_string());
+        let content = item.get("content").cloned().unwrap_or(Value::Null);
+        let title = item
+            .get("title")
+            .or_else(|| content.get("title"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .trim();
+        if title.is_empty() {
+            out.push(Err(SkippedTask { source, reason: "no title".into() }));
+            continue;
+        }
+        let mut card = CardFile::new_with_title(title);
+        let mut unmapped = vec![];
+        let mut status = String::new();
+        let fm = &mut card.front_matter;
+        for (key, v) in item.as_object().into_iter().flatten() {
+            let k = key.to_ascii_lowercase();
+            let s = v.as_str().map(|s| s.trim()).unwrap_or("");
+            match k.as_str() {
+                "id" | "title" | "content" | "repository" => {}
+                "status" => status = s.to_string(),
+                "labels" => {
+                    let l = string_list(Some(v));
+                    fm.labels = (!l.is_empty()).then_some(l);
+                }
+                "assignees" => {
+                    let a = string_list(Some(v));
+                    fm.assignees = (!a.is_empty()).then_some(a);
+                }
+                "priority" => {
+                    fm.priority = match s.to_ascii_lowercase().as_str() {
+                        "p0" | "urgent" | "critical" => Some("P0".into()),
+                        "p1" | "high" => Some("P1".into()),
+                        "p2" | "medium" => Some("P2".into()),
+                        "p3" | "low" => Some("P3".into()),
+                        _ => {
+                            unmapped.push(format!("priority {}", unmapped_value(v)));
+                            None
+                        }
+                    }
+                }
+                "size" | "estimate" => match v.as_u64().map(|n| n as u32).or_else(|| cfg.parse_size(s)) {
+                    Some(n) => fm.size = Some(n),
+                    None => unmapped.push(format!("{key}: {}", unmapped_value(v))),
+                },
+                "due" | "due date" | "duedate" | "due_date" | "target date" if kanban_model::parse_due_date(s).is_some() => {
+                    fm.due_date = Some(s.to_string())
+                }
+                _ => unmapped.push(format!("{key}: {}", unmapped_value(v))),
+            }
+        }
+        if let Some(body) = content.get("body").and_then(|v| v.as_str()).filter(|b| !b.trim().is_empty()) {
+            card.body = format!("{}\n", body.trim_end());
+        }
+        if let Some(url) = content.get("url").and_then(|v| v.as_str()) {
+            let name = match (content.get("repository").and_then(|v| v.as_str()), content.get("number").and_then(|v| v.as_u64())) {
+                (Some(repo), Some(n)) => format!("{repo}#{n}"),
+                _ => "GitHub".into(),
+            };
+            card.front_matter.links = Some(vec![kanban_model::CardLink { title: Some(name), url: url.to_string() }]);
+        }
+        out.push(Ok(ForeignTask { source, status, card, unmapped }));
+    }
+    Ok(out)
+}
+
+/// Result of [`Board::scan_code_todos`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TodoScan {
+    /// Files read under the scanned directory
+    pub files: usize,
+    /// `TODO(kanban:<ID>)` markers found
+    pub markers: usize,
+    /// Cards whose `## Code references` section changed (or would, on a dry run)
+    pub updated: Vec<String>,
+    /// Markers pointing at done-like or unknown cards
+    pub flagged: Vec<FlaggedTodo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CodeTodo {
+    file: String,
+    line: usize,
+    text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FlaggedTodo {
+    pub id: String,
+    pub file: String,
+    pub line: usize,
+    /// `done` or `missing`
+    pub reason: String,
+}
+
+/// Split a card body into the text outside `## Code references` and that section's list items.
+fn split_code_refs(body: &str) -> (String, Vec<String>) {
+    let mut rest = String::new();
+    let mut items = vec![];
+    let mut inside = false;
+    for line in body.lines() {
+        if line.trim_end() == "## Code references" {
+            inside = true;
+            continue;
+        }
+        if inside && line.starts_with('#') {
+            inside = false;
+        }
+        if inside {
+            if line.starts_with("- ") {
+                items.push(line.trim_end().to_string());
+            }
+        } else {
+            rest.push_str(line);
+            rest.push('\n');
+        }
+    }
+    (rest, items)
+}
+
+/// `- `path:line` text` -> (path, line, text)
+fn parse_code_ref(item: &str) -> Option<(String, usize, String)> {
+    let item = item.strip_prefix("- `")?;
+    let (loc, text) = item.split_once('`')?;
+    let (file, line) = loc.rsplit_once(':')?;
+    Some((file.to_string(), line.parse().ok()?, text.trim().to_string()))
+}
+
+/// Result of [`Board::clone_layout_into`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CloneReport {
+    /// Copied layout files, relative to `.kanban/`
+    pub files: Vec<String>,
+    pub cards: Vec<ClonedCard>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClonedCard {
+    /// Id in the source board
+    pub from: String,
+    /// New id in the cloned board
+    pub id: String,
+    pub column: String,
+}
+
+/// Outgoing edges declared in one card's front matter (parent, depends_on, relates).
+pub fn relation_edges_of(card: &CardFile) -> Vec<RelationEdge> {
+    let fm = &card.front_matter;
+    let idu = fm.id.to_uppercase();
+    let mut out = vec![];
+    if let Some(p) = fm.parent.as_deref() {
+        out.push(RelationEdge::new("parent", &idu, p));
+    }
+    for d in fm.depends_on.as_deref().unwrap_or_default() {
+        out.push(RelationEdge::new("depends", &idu, d));
+    }
+    for r in fm.relates.as_deref().unwrap_or_default() {
+        out.push(RelationEdge::new("relates", &idu, r));
+    }
+    out
+}
+
+/// Per-card records appended before relations.ndjson is compacted
+const RELATIONS_COMPACT_AFTER: usize = 256;
+
+/// Sidecar of relations.ndjson counting the per-card records appended since the last rewrite
+const RELATIONS_RECORDS_FILE: &str = "relations.records";
+
+/// Operations kept in operations.ndjson (how far `kanban_undo` can go back)
+pub const OPERATIONS_KEPT: usize = 50;
+
+/// Per-card relations.ndjson record: the full outgoing edge set of `card`
+/// (`{"card":"01A..","parent":"01P..","depends":[..],"relates":[..]}`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct CardRelations {
+    card: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    depends: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    relates: Vec<String>,
+}
+
+impl CardRelations {
+    fn edges(&self, id: &str) -> Vec<RelationEdge> {
+        self.parent
+            .iter()
+            .map(|p| RelationEdge::new("parent", id, p))
+            .chain(self.depends.iter().map(|d| RelationEdge::new("depends", id, d)))
+            .chain(self.relates.iter().map(|r| RelationEdge::new("relates", id, r)))
+            .collect()
+    }
+}
+
+/// One relations.ndjson row. Ids are normalized to uppercase, `type` to lowercase.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RelationEdge {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl RelationEdge {
+    pub fn new(kind: &str, from: &str, to: &str) -> Self {
+        Self {
+            kind: kind.to_lowercase(),
+            from: from.to_uppercase(),
+            to: to.to_uppercase(),
+        }
+    }
+}
+
+/// Result of [`Board::verify_relations_index`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelationsDrift {
+    /// In front matter but not in the index
+    pub missing: Vec<RelationEdge>,
+    /// In the index but not in front matter
+    pub stale: Vec<RelationEdge>,
+}
+
+impl RelationsDrift {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.stale.is_empty()
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// Filters for `Board::query`. Every set field must match; text fields compare case-insensitively.
+#[derive(Debug, Clone, Default)]
 pub struct ListFilter {
+    /// Columns to look in; `None` = every non-done column (index, then columns.toml, then defaults)
     pub columns: Option<Vec<String>>,
     pub lane: Option<String>,
     pub priority: Option<String>,
     pub label: Option<String>,
+    /// Assignee id or a `[people]` alias
     pub assignee: Option<String>,
+    /// Substring of title, body or id (scans card files); with `fuzzy`, title similarity (index only)
     pub query: Option<String>,
+    pub fuzzy: bool,
+    /// Also look in done-like columns
     pub include_done: bool,
-    pub offset: Option<usize>,
-    pub limit: Option<usize>,
+    /// `completed_at` bounds for done-like cards; either implies `include_done`
+    pub completed_since: Option<OffsetDateTime>,
+    pub completed_until: Option<OffsetDateTime>,
+    /// `resolution` of done-like cards (case-insensitive); implies `include_done`
+    pub resolution: Option<String>,
+    /// Only cards that entered their column at least this long ago
+    pub older_than: Option<time::Duration>,
+    pub blocked: Option<bool>,
+    /// Only direct children of this card (relations index, case-insensitive)
+    pub parent: Option<String>,
+    /// false: only top-level cards; true: only cards that have a parent
+    pub has_parent: Option<bool>,
+    /// `due_date` bounds (inclusive); cards without a due date never match
+    pub due_before: Option<OffsetDateTime>,
+    pub due_after: Option<OffsetDateTime>,
+    /// true: open cards past their due date; false: everything else
+    pub overdue: Option<bool>,
+    /// Ordering; `None` = `[list] default_sort` (fuzzy matches rank by score first)
+    pub sort: Option<Vec<kanban_model::SortKey>>,
+}
+
+/// One card as listed: a cards.ndjson row, or the same fields read from a card file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CardSummary {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub column: String,
+    #[serde(default)]
+    pub lane: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub size: Option<u32>,
+    #[serde(default)]
+    pub labels: Option<Vec<String>>,
+    #[serde(default)]
+    pub assignees: Option<Vec<String>>,
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    #[serde(default)]
+    pub resolution: Option<String>,
+    #[serde(default)]
+    pub position: Option<i64>,
+    #[serde(default)]
+    pub due_date: Option<String>,
+    #[serde(default)]
+    pub blocked: bool,
+    /// When the card entered its current column (RFC3339)
+    #[serde(default)]
+    pub in_column_since: Option<String>,
+    /// Relative to the board root
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The path was derived from column and title (index row without a path)
+    #[serde(skip)]
+    pub path_is_guess: bool,
+    /// Fuzzy title similarity (0..=1), set by fuzzy queries only
+    #[serde(skip)]
+    pub score: Option<f64>,
+    /// Context of the first body match, set by substring queries that matched the body
+    #[serde(skip)]
+    pub snippet: Option<kanban_model::TextSnippet>,
+    /// Card body; held only while a substring query scans files
+    #[serde(skip)]
+    body: String,
+}
+
+impl CardSummary {
+    fn from_card(card: &CardFile, column: &str, path: String, in_column_since: Option<String>) -> Self {
+        let fm = &card.front_matter;
+        Self {
+            id: fm.id.to_uppercase(),
+            title: fm.title.clone(),
+            description: fm.description.clone(),
+            column: column.to_string(),
+            lane: fm.lane.clone(),
+            priority: fm.priority.clone(),
+            size: fm.size,
+            labels: fm.labels.clone(),
+            assignees: fm.assignees.clone(),
+            completed_at: fm.completed_at.clone(),
+            updated_at: fm.updated_at.clone(),
+            resolution: fm.resolution.clone(),
+            position: fm.position,
+            due_date: fm.due_date.clone(),
+            blocked: fm.blocked.unwrap_or(false),
+            in_column_since,
+            path: Some(path),
+            path_is_guess: false,
+            score: None,
+            snippet: None,
+            body: card.body.clone(),
+        }
+    }
+
+    pub fn sort_fields(&self) -> kanban_model::SortFields {
+        kanban_model::SortFields {
+            id: self.id.to_uppercase(),
+            priority: self.priority.clone(),
+            position: self.position,
+            title: self.title.clone(),
+            size: self.size,
+            updated_at: self.updated_at.clone(),
+        }
+    }
+}
+
+/// One `.kanban/done.ndjson` row: a card as filed into a done-like column. The file is
+/// append-only; the last row per id wins and a `reopened` row cancels the earlier ones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DoneRecord {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    #[serde(default)]
+    pub labels: Option<Vec<String>>,
+    #[serde(default)]
+    pub size: Option<u32>,
+    #[serde(default)]
+    pub resolution: Option<String>,
+    #[serde(default)]
+    pub column: String,
+    /// Relative to the board root
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The card left the done-like columns again
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reopened: bool,
+}
+
+impl DoneRecord {
+    fn from_card(card: &CardFile, column: &str, path: String) -> Self {
+        let fm = &card.front_matter;
+        Self {
+            id: fm.id.to_uppercase(),
+            title: fm.title.clone(),
+            completed_at: fm.completed_at.clone(),
+            labels: fm.labels.clone(),
+            size: fm.size,
+            resolution: fm.resolution.clone(),
+            column: column.to_string(),
+            path: Some(path),
+            reopened: false,
+        }
+    }
+
+    fn into_summary(self) -> CardSummary {
+        CardSummary {
+            id: self.id,
+            title: self.title,
+            column: self.column,
+            size: self.size,
+            labels: self.labels,
+            in_column_since: self.completed_at.clone(),
+            completed_at: self.completed_at,
+            resolution: self.resolution,
+            path: self.path,
+            ..Default::default()
+        }
+    }
+}
+
+/// Subtree totals from `Board::rollup`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rollup {
+    pub done: u32,
+    pub total: u32,
+    pub done_size: u32,
+    pub total_size: u32,
+    /// Checklist items of the root and its descendants
+    pub checklist_done: u32,
+    pub checklist_total: u32,
+}
+
+/// Where a query read its cards from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuerySource {
+    Index,
+    FsScan,
+}
+
+/// How `Board::query_with_stats` ran (kanban_list `explain`).
+#[derive(Debug, Clone)]
+pub struct QueryStats {
+    pub source: QuerySource,
+    /// Why the card files were scanned instead of the index
+    pub reason: Option<&'static str>,
+    /// Columns searched (the defaulted scope when the filter named none)
+    pub columns: Vec<String>,
+    /// Applied ordering
+    pub sort: Vec<kanban_model::SortKey>,
+    pub index_rows: usize,
+    pub files_read: usize,
+    /// The index would have served the query but cards.ndjson does not exist
+    pub index_missing: bool,
+    /// Done-like cards came from done.ndjson (completed-date and resolution queries)
+    pub done_index: bool,
 }
 
 impl Board {
-    pub fn list_cards_filtered(&self, _filter: &ListFilter) -> Result<Vec<String>> {
-        // Minimal stub
-        Ok(vec![])
+    /// Cards matching `filter`, ordered by its sort.
+    pub fn query(&self, filter: &ListFilter) -> Result<Vec<CardSummary>> {
+        Ok(self.query_with_stats(filter)?.0)
+    }
+
+    /// Like `query`, also reporting where the cards came from. cards.ndjson serves everything
+    /// except substring queries (they need card bodies) and boards without an index.
+    pub fn query_with_stats(&self, filter: &ListFilter) -> Result<(Vec<CardSummary>, QueryStats)> {
+        let cfg = kanban_model::ColumnsToml::load(&self.root);
+        let done_like = DoneLike::new(&cfg);
+        let base = self.root.join(".kanban");
+        let idx = base.join("cards.ndjson");
+        let index_text = fs_err::read_to_string(&idx).ok();
+        let rows: Vec<CardSummary> = index_text
+            .as_deref()
+            .unwrap_or_default()
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .filter_map(|l| serde_json::from_str::<CardSummary>(l).ok())
+            .collect();
+        let columns = match &filter.columns {
+            Some(c) => c.clone(),
+            None => Self::default_list_columns(&cfg, &done_like, &rows),
+        };
+        let historical =
+            filter.completed_since.is_some() || filter.completed_until.is_some() || filter.resolution.is_some();
+        let include_done = filter.include_done || historical;
+        let index_served = filter.query.is_none() || filter.fuzzy;
+        let use_index = index_served && index_text.is_some();
+        // 完了日・resolution の絞り込みでは done 系の列を done.ndjson から引きます
+        let done_rows = if historical && use_index { self.read_done_index() } else { None };
+        let now = kanban_model::now();
+        let lower = |o: &Option<String>| o.as_ref().map(|s| s.to_lowercase());
+        let (lane, priority, label) = (lower(&filter.lane), lower(&filter.priority), lower(&filter.label));
+        let resolution = lower(&filter.resolution);
+        let assignee = filter
+            .assignee
+            .as_deref()
+            .map(|a| cfg.people.resolve(a).unwrap_or(a).to_lowercase());
+        let query = lower(&filter.query);
+        let any_eq = |v: &Option<Vec<String>>, want: &str| v.iter().flatten().any(|s| s.eq_ignore_ascii_case(want));
+        // 親子の絞り込みは関係索引から親を引きます
+        let parents = if filter.parent.is_some() || filter.has_parent.is_some() {
+            self.parents_by_child()?
+        } else {
+            Default::default()
+        };
+        let parent = filter.parent.as_ref().map(|p| p.to_uppercase());
+        // 日付だけの期限はボードのタイムゾーンでその日の終わり
+        let due_mismatch = |c: &CardSummary| {
+            let due = c.due_date.as_deref().and_then(|d| kanban_model::parse_due_date_in(d, cfg.board_offset()));
+            let overdue = due.is_some_and(|d| d < now) && !done_like.contains(&c.column);
+            filter.due_before.is_some_and(|b| due.is_none_or(|d| d > b))
+                || filter.due_after.is_some_and(|a| due.is_none_or(|d| d < a))
+                || filter.overdue.is_some_and(|o| overdue != o)
+        };
+        let parent_mismatch = |c: &CardSummary| {
+            let of = parents.get(&c.id.to_uppercase());
+            parent.as_ref().is_some_and(|p| of != Some(p)) || filter.has_parent.is_some_and(|h| of.is_some() != h)
+        };
+        // 列以外の条件はここで一度だけ判定します（索引行とカードファイルで共通）
+        let matches = |c: &mut CardSummary| -> bool {
+            if lane.as_ref().is_some_and(|l| c.lane.as_ref().map(|s| s.to_lowercase()).as_ref() != Some(l))
+                || priority.as_ref().is_some_and(|p| c.priority.as_ref().map(|s| s.to_lowercase()).as_ref() != Some(p))
+                || label.as_ref().is_some_and(|l| !any_eq(&c.labels, l))
+                || assignee.as_ref().is_some_and(|a| !any_eq(&c.assignees, a))
+                || filter.blocked.is_some_and(|b| c.blocked != b)
+                || parent_mismatch(c)
+                || due_mismatch(c)
+                || resolution.as_ref().is_some_and(|r| {
+                    !done_like.contains(&c.column) || c.resolution.as_ref().map(|s| s.to_lowercase()).as_ref() != Some(r)
+                })
+            {
+                return false;
+            }
+            if done_like.contains(&c.column) {
+                let at = c.completed_at.as_deref().and_then(|s| OffsetDateTime::parse(s, &Rfc3339).ok());
+                let in_range = match at {
+                    Some(t) => {
+                        filter.completed_since.is_none_or(|s| t >= s) && filter.completed_until.is_none_or(|u| t <= u)
+                    }
+                    None => filter.completed_since.is_none() && filter.completed_until.is_none(),
+                };
+                if !in_range {
+                    return false;
+                }
+            }
+            if let Some(age) = filter.older_than {
+                let since = c.in_column_since.as_deref().and_then(|s| OffsetDateTime::parse(s, &Rfc3339).ok());
+                if since.is_none_or(|t| now - t < age) {
+                    return false;
+                }
+            }
+            match &query {
+                Some(q) if filter.fuzzy => {
+                    let score = kanban_model::fuzzy_score(q, &c.title);
+                    if score < kanban_model::FUZZY_MIN_SCORE {
+                        return false;
+                    }
+                    c.score = Some((score * 1000.0).round() / 1000.0);
+                }
+                Some(q) => {
+                    let in_body = c.body.to_lowercase().contains(q.as_str());
+                    if !(in_body || c.title.to_lowercase().contains(q.as_str()) || c.id.to_lowercase().contains(q.as_str())) {
+                        return false;
+                    }
+                    if in_body {
+                        c.snippet = kanban_model::text_snippet(&c.body, std::slice::from_ref(q));
+                    }
+                }
+                None => {}
+            }
+            true
+        };
+        let mut stats = QueryStats {
+            source: if use_index { QuerySource::Index } else { QuerySource::FsScan },
+            reason: None,
+            columns: columns.clone(),
+            sort: filter.sort.clone().unwrap_or_else(|| cfg.default_sort()),
+            index_rows: rows.len(),
+            files_read: 0,
+            index_missing: index_served && !use_index,
+            done_index: done_rows.is_some(),
+        };
+        let mut out: Vec<CardSummary> = vec![];
+        if use_index {
+            // 同じIDの行が複数あれば後の行を採用します
+            let mut by_id: std::collections::HashMap<String, CardSummary> = Default::default();
+            for r in rows {
+                if done_rows.is_some() && done_like.contains(&r.column) {
+                    continue;
+                }
+                by_id.insert(r.id.to_uppercase(), r);
+            }
+            for d in done_rows.into_iter().flatten() {
+                by_id.insert(d.id.to_uppercase(), d.into_summary());
+            }
+            for (_, mut c) in by_id {
+                if !(columns.contains(&c.column) || (include_done && done_like.contains(&c.column))) {
+                    continue;
+                }
+                if c.path.is_none() {
+                    let fname = filename_for(&c.id, &c.title);
+                    c.path = Some(if done_like.contains(&c.column) {
+                        // Unknown year/month; leave directory ambiguous (best-effort)
+                        format!(".kanban/{}/**/{}", c.column, fname)
+                    } else {
+                        format!(".kanban/{}/{}", c.column, fname)
+                    });
+                    c.path_is_guess = true;
+                }
+                if matches(&mut c) {
+                    out.push(c);
+                }
+            }
+        } else {
+            stats.reason = Some(if filter.query.is_some() && !filter.fuzzy {
+                "query searches card bodies, which the index does not hold"
+            } else {
+                "cards.ndjson not found (run kanban reindex)"
+            });
+            // inColumnSince: 索引の値（同じ列のときのみ）→ ファイルmtime
+            let since: std::collections::HashMap<(String, String), String> = index_text
+                .as_deref()
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|l| serde_json::from_str::<CardSummary>(l).ok())
+                .filter_map(|r| Some(((r.id.to_uppercase(), r.column), r.in_column_since?)))
+                .collect();
+            let mut scan: Vec<String> = columns.clone();
+            if include_done {
+                scan.extend(done_like.columns().iter().filter(|c| !columns.contains(c)).cloned());
+            }
+            for col in &scan {
+                for e in walkdir::WalkDir::new(base.join(col))
+                    .min_depth(1)
+                    .max_depth(done_like.scan_depth(col))
+                    .into_iter()
+                    .flatten()
+                    .filter(|e| e.file_type().is_file())
+                {
+                    let p = e.path();
+                    if !p.extension().and_then(|x| x.to_str()).is_some_and(|x| x.eq_ignore_ascii_case("md")) {
+                        continue;
+                    }
+                    let Ok(text) = fs_err::read_to_string(p) else { continue };
+                    stats.files_read += 1;
+                    let Ok(card) = CardFile::from_markdown(&text) else { continue };
+                    let entered = since
+                        .get(&(card.front_matter.id.to_uppercase(), col.clone()))
+                        .cloned()
+                        .or_else(|| {
+                            let m = fs_err::metadata(p).ok()?.modified().ok()?;
+                            OffsetDateTime::from(m).format(&Rfc3339).ok()
+                        });
+                    let rel = p.strip_prefix(&self.root).unwrap_or(p).to_string_lossy().to_string();
+                    let mut c = CardSummary::from_card(&card, col, rel, entered);
+                    if matches(&mut c) {
+                        c.body.clear();
+                        out.push(c);
+                    }
+                }
+            }
+        }
+        out.sort_by(|a, b| kanban_model::compare_by_sort(&stats.sort, &a.sort_fields(), &b.sort_fields()));
+        if filter.fuzzy && filter.query.is_some() {
+            // 類似度の高い順（同点は sort の順を保ちます）
+            out.sort_by(|a, b| b.score.unwrap_or(0.0).total_cmp(&a.score.unwrap_or(0.0)));
+        }
+        Ok((out, stats))
+    }
+
+    /// Default list scope: non-done columns found in cards.ndjson, else columns.toml, else
+    /// `[backlog, doing, review]` (deduplicated case-insensitively, order kept).
+    fn default_list_columns(cfg: &kanban_model::ColumnsToml, done_like: &DoneLike, rows: &[CardSummary]) -> Vec<String> {
+        let mut cols: Vec<String> = rows
+            .iter()
+            .map(|r| r.column.clone())
+            .filter(|c| !done_like.contains(c) && !c.trim().is_empty())
+            .collect();
+        if cols.is_empty() {
+            cols = if cfg.columns.is_empty() {
+                vec!["backlog".into(), "doing".into(), "review".into()]
+            } else {
+                cfg.columns.iter().filter(|c| !done_like.contains(c)).cloned().collect()
+            };
+        }
+        let mut seen = std::collections::HashSet::new();
+        cols.into_iter().filter(|c| seen.insert(c.to_lowercase())).collect()
     }
 
     pub fn upsert_card_index(
@@ -412,10 +3065,23 @@ impl Board {
         column: &str,
         path: &std::path::Path,
     ) -> anyhow::Result<()> {
+        self.upsert_card_indexes(&[(card, column, path)])
+    }
+
+    /// Upsert several cards.ndjson rows in one rewrite.
+    pub fn upsert_card_indexes(
+        &self,
+        entries: &[(&kanban_model::CardFile, &str, &std::path::Path)],
+    ) -> anyhow::Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
         let base = self.root.join(".kanban");
         fs_err::create_dir_all(&base)?;
         let idx = base.join("cards.ndjson");
         let mut lines: Vec<String> = Vec::new();
+        let mut since: std::collections::HashMap<String, String> = Default::default();
+        let mut prev_column: std::collections::HashMap<String, String> = Default::default();
         if idx.exists() {
             let text = fs_err::read_to_string(&idx)?;
             for line in text.lines() {
@@ -423,31 +3089,70 @@ impl Board {
                     continue;
                 }
                 if let Ok(v) = serde_json::from_str::<serde_json::Value>(line) {
-                    if v.get("id").and_then(|x| x.as_str()) == Some(card.front_matter.id.as_str()) {
+                    let id = v.get("id").and_then(|x| x.as_str()).unwrap_or("");
+                    if let Some((card, column, _)) = entries
+                        .iter()
+                        .find(|(c, _, _)| c.front_matter.id.eq_ignore_ascii_case(id))
+                    {
+                        if let Some(c) = v.get("column").and_then(|x| x.as_str()) {
+                            prev_column.insert(card.front_matter.id.to_uppercase(), c.to_string());
+                        }
+                        // 同じ列に留まる更新では列への入場時刻を引き継ぎます
+                        if v.get("column").and_then(|x| x.as_str()) == Some(*column) {
+                            if let Some(s) = v.get("in_column_since").and_then(|x| x.as_str()) {
+                                since.insert(card.front_matter.id.to_uppercase(), s.to_string());
+                            }
+                        }
                         continue;
                     }
                 }
                 lines.push(line.to_string());
             }
         }
-        let rel_path = path.strip_prefix(&self.root).unwrap_or(path).to_path_buf();
-        let v = json!({
-            "id": card.front_matter.id,
-            "title": card.front_matter.title,
-            "column": column,
-            "lane": card.front_matter.lane,
-            "priority": card.front_matter.priority,
-            "labels": card.front_matter.labels,
-            "assignees": card.front_matter.assignees,
-            "completed_at": card.front_matter.completed_at,
-            "path": rel_path.to_string_lossy(),
-        });
-        lines.push(serde_json::to_string(&v)?);
+        let now = kanban_model::now()
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        for (card, column, path) in entries {
+            let id = card.front_matter.id.to_uppercase();
+            let rel_path = path.strip_prefix(&self.root).unwrap_or(path).to_path_buf();
+            let v = json!({
+                "id": id,
+                "title": card.front_matter.title,
+                "description": card.front_matter.description,
+                "column": column,
+                "lane": card.front_matter.lane,
+                "priority": card.front_matter.priority,
+                "size": card.front_matter.size,
+                "labels": card.front_matter.labels,
+                "assignees": card.front_matter.assignees,
+                "completed_at": card.front_matter.completed_at,
+                "updated_at": card.front_matter.updated_at,
+                "resolution": card.front_matter.resolution,
+                "position": card.front_matter.position,
+                "due_date": card.front_matter.due_date,
+                "blocked": card.front_matter.blocked.unwrap_or(false),
+                "in_column_since": since.get(&id).cloned().unwrap_or_else(|| now.clone()),
+                "path": rel_path.to_string_lossy(),
+            });
+            lines.push(serde_json::to_string(&v)?);
+        }
         let mut tmp = tempfile::NamedTempFile::new_in(&base)?;
         for l in lines {
             writeln!(tmp, "{l}")?;
         }
         tmp.persist(idx)?;
+        let done_like = self.done_like();
+        let mut done_rows: Vec<DoneRecord> = vec![];
+        for (card, column, path) in entries {
+            let id = card.front_matter.id.to_uppercase();
+            if done_like.contains(column) {
+                let rel = path.strip_prefix(&self.root).unwrap_or(path).to_string_lossy().to_string();
+                done_rows.push(DoneRecord::from_card(card, column, rel));
+            } else if prev_column.get(&id).is_some_and(|c| done_like.contains(c)) {
+                done_rows.push(DoneRecord { id, column: column.to_string(), reopened: true, ..Default::default() });
+            }
+        }
+        self.append_done_index(done_rows)?;
         Ok(())
     }
 }